@@ -0,0 +1,12 @@
+#![forbid(unsafe_code)]
+#![no_std]
+
+extern crate no_std_compat as std;
+
+#[macro_use] extern crate lazy_static;
+
+mod ast;
+mod rpcs;
+pub mod util;
+
+pub use ast::*;
@@ -10,16 +10,23 @@ use bin_pool::BinPool;
 
 use crate::*;
 use crate::gc::*;
+use crate::runtime::{System, RpcCheckError};
 
 /// Number of bytes to display on each line of a hex dump
 const BYTES_PER_LINE: usize = 12;
 
+/// The name of the local variable holding the inputs of a closure with empty slots (see [`Instruction::MakeClosure`]).
+/// This contains a null character, which cannot appear in project XML, so it can never conflict with a real variable.
+pub(crate) const EMPTY_SLOTS_VAR: &str = "\0empty slots";
+
 #[derive(Clone, Copy, Debug, FromPrimitive)]
 #[repr(u8)]
 pub(crate) enum BinaryOp {
     Add, Sub, Mul, Div, Mod, Pow, Log,
-    Greater, Less,
+    Greater, GreaterEq, Less, LessEq,
+    Atan2, Min, Max,
     SplitCustom,
+    StrGet, StrContains, StrIndexOf,
 }
 #[derive(Clone, Copy, Debug, FromPrimitive)]
 #[repr(u8)]
@@ -31,11 +38,43 @@ pub(crate) enum UnaryOp {
     Round, Floor, Ceil,
     Sin, Cos, Tan,
     Asin, Acos, Atan,
+    Sign,
     SplitLetter, SplitWord, SplitTab, SplitCR, SplitLF, SplitCsv, SplitJson,
-    Strlen,
+    Strlen, StrLower, StrUpper,
     UnicodeToChar, CharToUnicode,
 }
 
+/// A higher-order list operation which calls a closure on each item of a list (see [`Instruction::ListHof`]).
+#[derive(Clone, Copy, Debug, FromPrimitive, Collect)]
+#[collect(require_static)]
+#[repr(u8)]
+pub(crate) enum HofOp {
+    /// Returns a list of the results of calling the closure on each item.
+    Map,
+    /// Returns a list of the items for which the closure returns true.
+    Keep,
+    /// Returns the first item for which the closure returns true, or an empty string if there is no such item.
+    Find,
+    /// Returns the result of repeatedly calling the closure with the accumulated value and the next item, starting with the first item.
+    /// A list with one item gives that item, and an empty list gives zero.
+    Combine,
+    /// Returns a sorted copy of the list, where the closure is called with two items and returns true if the first should come before the second.
+    /// The sort is stable.
+    Sort,
+}
+
+/// A basic type of value which can be tested for by [`Instruction::TypeQuery`].
+#[derive(Clone, Copy, Debug, FromPrimitive)]
+#[repr(u8)]
+pub(crate) enum BasicType {
+    /// A number or a string which can be parsed as a number.
+    Number,
+    /// A string which cannot be parsed as a number.
+    Text,
+    Bool,
+    List,
+}
+
 impl From<BinaryOp> for Instruction<'_> { fn from(op: BinaryOp) -> Self { Self::BinaryOp { op } } }
 impl From<UnaryOp> for Instruction<'_> { fn from(op: UnaryOp) -> Self { Self::UnaryOp { op } } }
 
@@ -126,6 +165,40 @@ pub(crate) enum Instruction<'a> {
     /// Consumes one value, `list`, from the value stack and deletes all elements from it.
     ListRemoveAll,
 
+    /// Consumes `count` values from the value stack, which must be lists, and pushes their concatenation (in the original order) as a new list onto the value stack.
+    ListCat { count: usize },
+    /// Consumes two values, `list` and `value`, from the value stack and pushes the (1-based) index of the first item of `list` which is equal to `value`
+    /// (as in [`Instruction::Eq`]) onto the value stack, or zero if there is no such item.
+    ListFind,
+    /// Consumes 1 value, `list`, from the value stack and pushes a reversed (shallow) copy of `list` onto the value stack.
+    ListReverse,
+    /// Consumes 1 value, `list`, from the value stack and pushes a sorted (shallow) copy of `list` onto the value stack.
+    /// Numbers come first in ascending order, followed by all other items compared as case-insensitive text. The sort is stable. To sort with a custom ordering, see [`HofOp::Sort`].
+    ListSort,
+    /// Consumes 1 value, `list`, from the value stack and pushes a new list containing all the non-list items of `list` and its nested lists (in order) onto the value stack.
+    ListFlatten,
+    /// Consumes two values, `dims` and `list`, from the value stack, where `dims` is a number or a list of numbers.
+    /// Pushes a new (nested) list with the given dimensions onto the value stack, filled by repeating the items of [`Instruction::ListFlatten`] applied to `list`.
+    ListReshape,
+    /// Consumes 1 value, `list`, from the value stack and pushes a list of its dimensions onto the value stack.
+    /// Entry `i` of the result is the maximum length of any list at nesting depth `i`.
+    ListDims,
+    /// Consumes 1 value, `list`, from the value stack and pushes its rank (the number of dimensions, see [`Instruction::ListDims`]) onto the value stack.
+    ListRank,
+    /// Consumes 1 value, `list`, from the value stack and pushes its columns (i.e., its transpose) onto the value stack.
+    /// Missing items in short rows are filled with empty strings, and non-list items are repeated in every column.
+    ListColumns,
+    /// Consumes 1 value, `list`, from the value stack and pushes a list of `[value, count]` pairs onto the value stack, holding the number of times each distinct value occurs in `list`.
+    /// Values are compared as in [`Instruction::Eq`], and pairs are ordered by first occurrence.
+    ListDistribution,
+    /// Consumes 1 value, `list`, from the value stack and pushes its CSV encoding (as in RFC 4180) onto the value stack.
+    /// A list containing lists is encoded as a table with one line per item, each ending in a line break, and otherwise as a single line.
+    /// [`UnaryOp::SplitCsv`] uses the final line break to decode a one-row table as a table, so tables round-trip except that empty rows come back as one empty field.
+    ListCsv,
+    /// Consumes 1 value, `list`, from the value stack and pushes its JSON encoding onto the value stack.
+    /// Lists of `[key, value]` pairs are encoded as objects depending on the process's [`JsonObjectStrategy`](crate::runtime::JsonObjectStrategy).
+    ListJson,
+
     /// Consumes `args` values from the value stack in reverse order and concatenates them into a single string, which is then pushed onto the value stack.
     Strcat { args: usize },
 
@@ -135,8 +208,23 @@ pub(crate) enum Instruction<'a> {
     /// where `==` is a deep comparison allowing type conversions.
     /// This is similar to [`Instruction::BinaryOp`] except that it is not vectorized and always returns a single (scalar) boolean value.
     Eq,
+    /// Equivalent to [`Instruction::Eq`] except that the result is negated.
+    Neq,
+    /// Consumes 2 values, `b` and `a`, from the value stack, and pushes the (boolean) value `a is identical to b` onto the value stack.
+    /// Lists, closures, entities, and blobs are identical only if they are the same object; other values are compared as in [`Instruction::Eq`].
+    Identical,
     /// Consumes 1 value, `x`, from the value stack, and pushes the value `f(x)` onto the value stack.
     UnaryOp { op: UnaryOp },
+    /// Consumes 3 values, `end`, `start`, and `text`, from the value stack, and pushes the substring of `text`
+    /// from (1-based) character index `start` to `end` (inclusive) onto the value stack.
+    /// Indices are clamped to the bounds of the string. This is vectorized over `text`, but not `start` or `end`.
+    Substring,
+    /// Consumes 2 values, `b` and `a`, from the value stack, and pushes a random number between `a` and `b` (inclusive) onto the value stack.
+    /// If both bounds are integers, the result is an integer; otherwise it is a real number. This is vectorized like [`Instruction::BinaryOp`].
+    Random,
+    /// Consumes 1 value, `x`, from the value stack, and pushes the (boolean) value `x is a ty` onto the value stack.
+    /// Unlike [`Instruction::UnaryOp`], this is not vectorized, since lists are themselves a queryable type.
+    TypeQuery { ty: BasicType },
 
     /// Re/Declares a set of local variables, which are initialized to 0.
     /// Note that this is not equivalent to assigning a value of zero to the variable due to the potential issue of [`Shared::Aliased`].
@@ -163,14 +251,33 @@ pub(crate) enum Instruction<'a> {
     /// Consumes `captures` values from the meta stack (in reverse order), and then `params` values from the meta stack, each representing symbol names.
     /// Then creates a closure object with the given information.
     /// Captures are looked up and bound immediately based on the current execution context.
-    MakeClosure { pos: usize, params: usize, captures: usize },
+    ///
+    /// If there are no `params`, `empty_slots` is the number of empty input slots in the closure's code, which are implicit parameters (Snap-style).
+    /// Calling such a closure with one argument fills every slot with it, and otherwise the arguments fill the slots in order.
+    /// The slots are read from a list in the [`EMPTY_SLOTS_VAR`] local variable.
+    MakeClosure { pos: usize, params: usize, captures: usize, empty_slots: usize },
     /// Consumes 1 argument, `closure`, and another `args` arguments (in reverse order) from the value stack
     /// to assign to the parameters of `closure` before executing the closure's stored code.
-    /// It is an error if the number of supplied arguments does not match the number of parameters.
+    /// It is an error if the number of supplied arguments does not match the number of parameters (see [`Instruction::MakeClosure`] for closures with empty slots).
     CallClosure { args: usize },
+    /// Consumes 2 values, `closure` and `args`, from the value stack, where `args` is a list.
+    /// This is equivalent to [`Instruction::CallClosure`] with the items of `args` as the arguments.
+    CallClosureDyn,
+    /// Consumes 2 values, `closure` and `list`, from the value stack, and performs the higher-order operation `op` (see [`HofOp`])
+    /// by calling `closure` on each item of (a snapshot of) `list`, after which the result is pushed onto the value stack.
+    /// The closure is passed the item and, if it has more parameters, the (1-based) index of the item and the list itself.
+    /// For [`HofOp::Combine`], the accumulated value is passed before the item, and [`HofOp::Sort`] instead passes two items to compare.
+    ///
+    /// This instruction is executed once to start the operation and again after each call to `closure` returns,
+    /// and it yields after each call unless in a warp section.
+    ListHof { op: HofOp },
     /// Consumes `args` values from the meta stack and value stack, representing arguments.
     /// Then calls the given RPC, awaits the result, and pushes the return value onto the value stack.
     CallRpc { service: &'a str, rpc: &'a str, args: usize },
+    /// Consumes 3 values, `args`, `rpc`, and `service`, from the value stack, where `args` is a list of `[name, value]` pairs.
+    /// Then calls the given RPC, awaits the result, and pushes the return value onto the value stack.
+    /// This is equivalent to [`Instruction::CallRpc`] except that the service, RPC, and argument names are computed at runtime.
+    CallRpcDyn,
     /// Pops a return address from the call stack and jumps to it.
     /// The return value is left on the top of the value stack.
     /// If the call stack is empty, this instead terminates the process
@@ -183,6 +290,9 @@ pub(crate) enum Instruction<'a> {
 
     /// Consumes 1 value `msg` from the value stack and prints it to the stored printer.
     Print,
+
+    /// Consumes 1 value, `secs`, from the value stack and pauses execution of the process for (at least) that many seconds.
+    Sleep,
 }
 
 pub(crate) enum RelocateInfo {
@@ -213,6 +323,22 @@ impl BinaryWrite for BinaryOp {
     }
 }
 
+impl BinaryRead<'_> for HofOp { fn read(code: &[u8], _: &[u8], start: usize) -> (Self, usize) { (Self::from_u8(code[start]).unwrap(), start + 1) } }
+impl BinaryWrite for HofOp {
+    fn append(val: &Self, code: &mut Vec<u8>, _: &mut BinPool, _: &mut Vec<RelocateInfo>) {
+        debug_assert_eq!(mem::size_of::<Self>(), 1);
+        code.push((*val) as u8)
+    }
+}
+
+impl BinaryRead<'_> for BasicType { fn read(code: &[u8], _: &[u8], start: usize) -> (Self, usize) { (Self::from_u8(code[start]).unwrap(), start + 1) } }
+impl BinaryWrite for BasicType {
+    fn append(val: &Self, code: &mut Vec<u8>, _: &mut BinPool, _: &mut Vec<RelocateInfo>) {
+        debug_assert_eq!(mem::size_of::<Self>(), 1);
+        code.push((*val) as u8)
+    }
+}
+
 impl BinaryRead<'_> for UnaryOp { fn read(code: &[u8], _: &[u8], start: usize) -> (Self, usize) { (Self::from_u8(code[start]).unwrap(), start + 1) } }
 impl BinaryWrite for UnaryOp {
     fn append(val: &Self, code: &mut Vec<u8>, _: &mut BinPool, _: &mut Vec<RelocateInfo>) {
@@ -395,8 +521,9 @@ impl<'a> BinaryRead<'a> for Instruction<'a> {
             39 => read_prefixed!(Instruction::MetaPush {} : value),
 
             40 => read_prefixed!(Instruction::Call {} : pos, params),
-            41 => read_prefixed!(Instruction::MakeClosure {} : pos, params, captures),
+            41 => read_prefixed!(Instruction::MakeClosure {} : pos, params, captures, empty_slots),
             42 => read_prefixed!(Instruction::CallClosure {} : args),
+            68 => read_prefixed!(Instruction::CallClosureDyn),
             43 => read_prefixed!(Instruction::CallRpc {} : service, rpc, args),
             44 => read_prefixed!(Instruction::Return),
 
@@ -405,6 +532,32 @@ impl<'a> BinaryRead<'a> for Instruction<'a> {
 
             47 => read_prefixed!(Instruction::Print),
 
+            48 => read_prefixed!(Instruction::Sleep),
+            49 => read_prefixed!(Instruction::CallRpcDyn),
+
+            50 => read_prefixed!(Instruction::Neq),
+            51 => read_prefixed!(Instruction::Identical),
+
+            52 => read_prefixed!(Instruction::Substring),
+
+            53 => read_prefixed!(Instruction::ListHof {} : op),
+
+            54 => read_prefixed!(Instruction::ListCat {} : count),
+            55 => read_prefixed!(Instruction::ListFind),
+            56 => read_prefixed!(Instruction::ListReverse),
+            57 => read_prefixed!(Instruction::ListSort),
+            58 => read_prefixed!(Instruction::ListFlatten),
+            59 => read_prefixed!(Instruction::ListReshape),
+            60 => read_prefixed!(Instruction::ListDims),
+            61 => read_prefixed!(Instruction::ListRank),
+            62 => read_prefixed!(Instruction::ListColumns),
+            63 => read_prefixed!(Instruction::ListDistribution),
+            66 => read_prefixed!(Instruction::ListCsv),
+            67 => read_prefixed!(Instruction::ListJson),
+
+            64 => read_prefixed!(Instruction::Random),
+            65 => read_prefixed!(Instruction::TypeQuery {} : ty),
+
             _ => unreachable!(),
         }
     }
@@ -467,11 +620,29 @@ impl BinaryWrite for Instruction<'_> {
             Instruction::ListRemoveRandom => append_prefixed!(27),
             Instruction::ListRemoveAll => append_prefixed!(28),
 
+            Instruction::ListCat { count } => append_prefixed!(54: count),
+            Instruction::ListFind => append_prefixed!(55),
+            Instruction::ListReverse => append_prefixed!(56),
+            Instruction::ListSort => append_prefixed!(57),
+            Instruction::ListFlatten => append_prefixed!(58),
+            Instruction::ListReshape => append_prefixed!(59),
+            Instruction::ListDims => append_prefixed!(60),
+            Instruction::ListRank => append_prefixed!(61),
+            Instruction::ListColumns => append_prefixed!(62),
+            Instruction::ListDistribution => append_prefixed!(63),
+            Instruction::ListCsv => append_prefixed!(66),
+            Instruction::ListJson => append_prefixed!(67),
+
             Instruction::Strcat { args } => append_prefixed!(29: args),
 
             Instruction::BinaryOp { op } => append_prefixed!(30: op),
             Instruction::Eq => append_prefixed!(31),
+            Instruction::Neq => append_prefixed!(50),
+            Instruction::Identical => append_prefixed!(51),
             Instruction::UnaryOp { op } => append_prefixed!(32: op),
+            Instruction::Substring => append_prefixed!(52),
+            Instruction::Random => append_prefixed!(64),
+            Instruction::TypeQuery { ty } => append_prefixed!(65: ty),
 
             Instruction::DeclareLocal { var } => append_prefixed!(33: move str var),
             Instruction::Assign { var } => append_prefixed!(34: move str var),
@@ -484,15 +655,20 @@ impl BinaryWrite for Instruction<'_> {
             Instruction::MetaPush { value } => append_prefixed!(39: move str value),
 
             Instruction::Call { pos, params } => append_prefixed!(40: move pos, params),
-            Instruction::MakeClosure { pos, params, captures } => append_prefixed!(41: move pos, params, captures),
+            Instruction::MakeClosure { pos, params, captures, empty_slots } => append_prefixed!(41: move pos, params, captures, empty_slots),
             Instruction::CallClosure { args } => append_prefixed!(42: args),
+            Instruction::CallClosureDyn => append_prefixed!(68),
+            Instruction::ListHof { op } => append_prefixed!(53: op),
             Instruction::CallRpc { service, rpc, args } => append_prefixed!(43: move str service, move str rpc, args),
+            Instruction::CallRpcDyn => append_prefixed!(49),
             Instruction::Return => append_prefixed!(44),
 
             Instruction::Broadcast { wait: false } => append_prefixed!(45),
             Instruction::Broadcast { wait: true } => append_prefixed!(46),
 
             Instruction::Print => append_prefixed!(47),
+
+            Instruction::Sleep => append_prefixed!(48),
         }
     }
 }
@@ -524,6 +700,7 @@ struct ByteCodeBuilder<'a> {
     ins: Vec<InternalInstruction<'a>>,
     call_holes: Vec<(usize, &'a ast::FnRef, Option<&'a ast::Entity>)>, // (hole pos, function, entity)
     closure_holes: VecDeque<(usize, &'a [ast::VariableDef], &'a [ast::VariableRef], &'a [ast::Stmt], Option<&'a ast::Entity>)>, // (hole pos, params, captures, stmts, entity)
+    empty_slots: Option<usize>, // number of empty slots found so far in a closure with no params (or none if not in such a closure)
 }
 impl<'a> ByteCodeBuilder<'a> {
     fn append_simple_ins(&mut self, entity: Option<&'a ast::Entity>, values: &[&'a ast::Expr], op: Instruction<'a>) {
@@ -532,20 +709,36 @@ impl<'a> ByteCodeBuilder<'a> {
         }
         self.ins.push(op.into());
     }
+    fn append_value(&mut self, value: &'a ast::Value) {
+        match value {
+            ast::Value::Number(v) => self.ins.push(Instruction::PushNumber { value: *v }.into()),
+            ast::Value::String(v) => self.ins.push(Instruction::PushString { value: v }.into()),
+            ast::Value::Constant(v) => self.ins.push(Instruction::PushNumber {
+                value: match v {
+                    ast::Constant::Pi => std::f64::consts::PI,
+                    ast::Constant::E => std::f64::consts::E,
+                }
+            }.into()),
+            ast::Value::Bool(v) => self.ins.push(Instruction::PushBool { value: *v }.into()),
+            ast::Value::List(values) => {
+                // build a fresh list each time, since list literals are mutable
+                for value in values {
+                    self.append_value(value);
+                }
+                self.ins.push(Instruction::MakeList { len: values.len() }.into());
+            }
+        }
+    }
     fn append_expr(&mut self, expr: &'a ast::Expr, entity: Option<&'a ast::Entity>) {
         match expr {
-            ast::Expr::Value(v) => self.ins.push(match v {
-                ast::Value::Number(v) => Instruction::PushNumber { value: *v },
-                ast::Value::String(v) => Instruction::PushString { value: v },
-                ast::Value::Constant(v) => Instruction::PushNumber {
-                    value: match v {
-                        ast::Constant::Pi => std::f64::consts::PI,
-                        ast::Constant::E => std::f64::consts::E,
-                    }
-                },
-                ast::Value::Bool(v) => Instruction::PushBool { value: *v },
-                ast::Value::List(_) => unreachable!(),
-            }.into()),
+            ast::Expr::Value(ast::Value::String(v)) if v.is_empty() && self.empty_slots.is_some() => {
+                let slot = self.empty_slots.as_mut().unwrap();
+                *slot += 1;
+                self.ins.push(Instruction::PushNumber { value: *slot as f64 }.into());
+                self.ins.push(Instruction::PushVariable { var: EMPTY_SLOTS_VAR }.into());
+                self.ins.push(Instruction::ListGet.into());
+            }
+            ast::Expr::Value(v) => self.append_value(v),
             ast::Expr::Variable { var, .. } => self.ins.push(Instruction::PushVariable { var: &var.trans_name }.into()),
             ast::Expr::Add { left, right, .. } => self.append_simple_ins(entity, &[left, right], BinaryOp::Add.into()),
             ast::Expr::Sub { left, right, .. } => self.append_simple_ins(entity, &[left, right], BinaryOp::Sub.into()),
@@ -553,7 +746,9 @@ impl<'a> ByteCodeBuilder<'a> {
             ast::Expr::Div { left, right, .. } => self.append_simple_ins(entity, &[left, right], BinaryOp::Div.into()),
             ast::Expr::Pow { base, power, .. } => self.append_simple_ins(entity, &[base, power], BinaryOp::Pow.into()),
             ast::Expr::Greater { left, right, .. } => self.append_simple_ins(entity, &[left, right], BinaryOp::Greater.into()),
+            ast::Expr::GreaterEq { left, right, .. } => self.append_simple_ins(entity, &[left, right], BinaryOp::GreaterEq.into()),
             ast::Expr::Less { left, right, .. } => self.append_simple_ins(entity, &[left, right], BinaryOp::Less.into()),
+            ast::Expr::LessEq { left, right, .. } => self.append_simple_ins(entity, &[left, right], BinaryOp::LessEq.into()),
             ast::Expr::Mod { left, right, .. } => self.append_simple_ins(entity, &[left, right], BinaryOp::Mod.into()),
             ast::Expr::Log { base, value, .. } => self.append_simple_ins(entity, &[base, value], BinaryOp::Log.into()),
             ast::Expr::Neg { value, .. } => self.append_simple_ins(entity, &[value], UnaryOp::Neg.into()),
@@ -565,19 +760,59 @@ impl<'a> ByteCodeBuilder<'a> {
             ast::Expr::Asin { value, .. } => self.append_simple_ins(entity, &[value], UnaryOp::Asin.into()),
             ast::Expr::Acos { value, .. } => self.append_simple_ins(entity, &[value], UnaryOp::Acos.into()),
             ast::Expr::Atan { value, .. } => self.append_simple_ins(entity, &[value], UnaryOp::Atan.into()),
+            ast::Expr::Atan2 { y, x, .. } => self.append_simple_ins(entity, &[y, x], BinaryOp::Atan2.into()),
+            ast::Expr::Sign { value, .. } => self.append_simple_ins(entity, &[value], UnaryOp::Sign.into()),
+            ast::Expr::Min { left, right, .. } => self.append_simple_ins(entity, &[left, right], BinaryOp::Min.into()),
+            ast::Expr::Max { left, right, .. } => self.append_simple_ins(entity, &[left, right], BinaryOp::Max.into()),
+            ast::Expr::TypeQuery { value, ty, .. } => {
+                let ty = match ty {
+                    ast::ValueType::Number => BasicType::Number,
+                    ast::ValueType::Text => BasicType::Text,
+                    ast::ValueType::Bool => BasicType::Bool,
+                    ast::ValueType::List => BasicType::List,
+                };
+                self.append_simple_ins(entity, &[value], Instruction::TypeQuery { ty });
+            }
             ast::Expr::Round { value, .. } => self.append_simple_ins(entity, &[value], UnaryOp::Round.into()),
             ast::Expr::Floor { value, .. } => self.append_simple_ins(entity, &[value], UnaryOp::Floor.into()),
             ast::Expr::Ceil { value, .. } => self.append_simple_ins(entity, &[value], UnaryOp::Ceil.into()),
             ast::Expr::Not { value, .. } => self.append_simple_ins(entity, &[value], UnaryOp::Not.into()),
             ast::Expr::Strlen { value, .. } => self.append_simple_ins(entity, &[value], UnaryOp::Strlen.into()),
+            ast::Expr::StrLower { value, .. } => self.append_simple_ins(entity, &[value], UnaryOp::StrLower.into()),
+            ast::Expr::StrUpper { value, .. } => self.append_simple_ins(entity, &[value], UnaryOp::StrUpper.into()),
+            ast::Expr::StrGet { string, index, .. } => self.append_simple_ins(entity, &[index, string], BinaryOp::StrGet.into()),
+            ast::Expr::StrContains { string, pattern, .. } => self.append_simple_ins(entity, &[string, pattern], BinaryOp::StrContains.into()),
+            ast::Expr::StrFind { string, pattern, .. } => self.append_simple_ins(entity, &[pattern, string], BinaryOp::StrIndexOf.into()),
+            ast::Expr::Substring { string, start, end, .. } => self.append_simple_ins(entity, &[string, start, end], Instruction::Substring),
             ast::Expr::UnicodeToChar { value, .. } => self.append_simple_ins(entity, &[value], UnaryOp::UnicodeToChar.into()),
             ast::Expr::CharToUnicode { value, .. } => self.append_simple_ins(entity, &[value], UnaryOp::CharToUnicode.into()),
             ast::Expr::Eq { left, right, .. } => self.append_simple_ins(entity, &[left, right], Instruction::Eq),
+            ast::Expr::Neq { left, right, .. } => self.append_simple_ins(entity, &[left, right], Instruction::Neq),
+            ast::Expr::RandInclusive { a, b, .. } => self.append_simple_ins(entity, &[a, b], Instruction::Random),
+            ast::Expr::Identical { left, right, .. } => self.append_simple_ins(entity, &[left, right], Instruction::Identical),
             ast::Expr::ListIndex { list, index, .. } => self.append_simple_ins(entity, &[index, list], Instruction::ListGet),
             ast::Expr::ListLastIndex { list, .. } => self.append_simple_ins(entity, &[list], Instruction::ListGetLast),
             ast::Expr::ListRandIndex { list, .. } => self.append_simple_ins(entity, &[list], Instruction::ListGetRandom),
             ast::Expr::Listlen { value, .. } => self.append_simple_ins(entity, &[value], Instruction::ListLen),
             ast::Expr::ListIsEmpty { value, .. } => self.append_simple_ins(entity, &[value], Instruction::ListIsEmpty),
+            ast::Expr::ListFind { list, value, .. } => self.append_simple_ins(entity, &[value, list], Instruction::ListFind),
+            ast::Expr::ListReverse { value, .. } => self.append_simple_ins(entity, &[value], Instruction::ListReverse),
+            ast::Expr::ListSort { value, .. } => self.append_simple_ins(entity, &[value], Instruction::ListSort),
+            ast::Expr::ListSortBy { list, f, .. } => self.append_simple_ins(entity, &[list, f], Instruction::ListHof { op: HofOp::Sort }),
+            ast::Expr::ListFlatten { value, .. } => self.append_simple_ins(entity, &[value], Instruction::ListFlatten),
+            ast::Expr::ListDims { value, .. } => self.append_simple_ins(entity, &[value], Instruction::ListDims),
+            ast::Expr::ListRank { value, .. } => self.append_simple_ins(entity, &[value], Instruction::ListRank),
+            ast::Expr::ListColumns { value, .. } => self.append_simple_ins(entity, &[value], Instruction::ListColumns),
+            ast::Expr::ListDistribution { value, .. } => self.append_simple_ins(entity, &[value], Instruction::ListDistribution),
+            ast::Expr::ListReshape { value, dims, .. } => self.append_simple_ins(entity, &[value, dims], Instruction::ListReshape),
+            ast::Expr::ListCsv { value, .. } => self.append_simple_ins(entity, &[value], Instruction::ListCsv),
+            ast::Expr::ListJson { value, .. } => self.append_simple_ins(entity, &[value], Instruction::ListJson),
+            ast::Expr::Listcat { lists, .. } => {
+                for list in lists {
+                    self.append_expr(list, entity);
+                }
+                self.ins.push(Instruction::ListCat { count: lists.len() }.into());
+            }
             ast::Expr::RangeInclusive { start, stop, .. } => self.append_simple_ins(entity, &[start, stop], Instruction::MakeListRange),
             ast::Expr::MakeList { values, .. } => {
                 for value in values {
@@ -643,6 +878,11 @@ impl<'a> ByteCodeBuilder<'a> {
                 self.append_expr(closure, entity);
                 self.ins.push(Instruction::CallClosure { args: args.len() }.into());
             }
+            ast::Expr::CallClosureDyn { closure, args, .. } => self.append_simple_ins(entity, &[args, closure], Instruction::CallClosureDyn),
+            ast::Expr::Map { f, list, .. } => self.append_simple_ins(entity, &[list, f], Instruction::ListHof { op: HofOp::Map }),
+            ast::Expr::Keep { f, list, .. } => self.append_simple_ins(entity, &[list, f], Instruction::ListHof { op: HofOp::Keep }),
+            ast::Expr::FindFirst { f, list, .. } => self.append_simple_ins(entity, &[list, f], Instruction::ListHof { op: HofOp::Find }),
+            ast::Expr::Combine { list, f, .. } => self.append_simple_ins(entity, &[list, f], Instruction::ListHof { op: HofOp::Combine }),
             ast::Expr::CallRpc { service, rpc, args, .. } => {
                 for (arg_name, arg) in args {
                     self.ins.push(Instruction::MetaPush { value: arg_name }.into());
@@ -650,6 +890,7 @@ impl<'a> ByteCodeBuilder<'a> {
                 }
                 self.ins.push(Instruction::CallRpc { service, rpc, args: args.len() }.into());
             }
+            ast::Expr::CallRpcDyn { service, rpc, args, .. } => self.append_simple_ins(entity, &[service, rpc, args], Instruction::CallRpcDyn),
             ast::Expr::Closure { params, captures, stmts, .. } => {
                 let closure_hole_pos = self.ins.len();
                 self.ins.push(InternalInstruction::Illegal);
@@ -695,6 +936,7 @@ impl<'a> ByteCodeBuilder<'a> {
             ast::Stmt::LastIndexAssign { list, value, .. } => self.append_simple_ins(entity, &[list, value], Instruction::ListAssignLast),
             ast::Stmt::RandIndexAssign { list, value, .. } => self.append_simple_ins(entity, &[list, value], Instruction::ListAssignRandom),
             ast::Stmt::Return { value, .. } => self.append_simple_ins(entity, &[value], Instruction::Return),
+            ast::Stmt::Sleep { seconds, .. } => self.append_simple_ins(entity, &[seconds], Instruction::Sleep),
             ast::Stmt::Say { content, duration, .. } | ast::Stmt::Think { content, duration, .. } => match duration {
                 Some(_) => unimplemented!(),
                 None => self.append_simple_ins(entity, &[content], Instruction::Print),
@@ -722,6 +964,22 @@ impl<'a> ByteCodeBuilder<'a> {
                 self.ins.push(Instruction::CallClosure { args: args.len() }.into());
                 self.ins.push(Instruction::PopValue.into());
             }
+            ast::Stmt::RunRpc { service, rpc, args, .. } => {
+                for (arg_name, arg) in args {
+                    self.ins.push(Instruction::MetaPush { value: arg_name }.into());
+                    self.append_expr(arg, entity);
+                }
+                self.ins.push(Instruction::CallRpc { service, rpc, args: args.len() }.into());
+                self.ins.push(Instruction::PopValue.into());
+            }
+            ast::Stmt::RunRpcDyn { service, rpc, args, .. } => {
+                self.append_simple_ins(entity, &[service, rpc, args], Instruction::CallRpcDyn);
+                self.ins.push(Instruction::PopValue.into());
+            }
+            ast::Stmt::RunClosureDyn { closure, args, .. } => {
+                self.append_simple_ins(entity, &[args, closure], Instruction::CallClosureDyn);
+                self.ins.push(Instruction::PopValue.into());
+            }
             ast::Stmt::Warp { stmts, .. } => {
                 self.ins.push(Instruction::WarpStart.into());
                 for stmt in stmts {
@@ -1056,6 +1314,13 @@ impl<'a> ByteCodeBuilder<'a> {
 }
 
 impl ByteCode {
+    /// Links a raw sequence of instructions, starting at position zero, for testing instructions which cannot be generated from a project file.
+    /// Code addresses (e.g., jump targets) are given as indices into `ins`.
+    #[cfg(test)]
+    pub(crate) fn assemble(ins: Vec<Instruction>) -> ByteCode {
+        let code = ByteCodeBuilder { ins: ins.into_iter().map(Into::into).collect(), ..Default::default() };
+        code.finalize(Locations { funcs: vec![], entities: vec![] }).0
+    }
     /// Compiles a single project role into an executable form.
     /// Also emits the symbol table of functions and scripts,
     /// which is needed to execute a specific segment of code.
@@ -1087,7 +1352,9 @@ impl ByteCode {
 
         while let Some((hole_pos, params, captures, stmts, entity)) = code.closure_holes.pop_front() {
             let pos = code.ins.len();
+            code.empty_slots = if params.is_empty() { Some(0) } else { None };
             code.append_stmts_ret(stmts, entity);
+            let empty_slots = code.empty_slots.take().unwrap_or(0);
 
             let mut ins_pack = Vec::with_capacity(params.len() + captures.len() + 1);
             for param in params {
@@ -1096,13 +1363,43 @@ impl ByteCode {
             for param in captures {
                 ins_pack.push(Instruction::MetaPush { value: &param.trans_name });
             }
-            ins_pack.push(Instruction::MakeClosure { pos, params: params.len(), captures: captures.len() }.into());
+            ins_pack.push(Instruction::MakeClosure { pos, params: params.len(), captures: captures.len(), empty_slots }.into());
 
             code.ins[hole_pos] = InternalInstruction::Packed(ins_pack);
         }
 
         code.link(Locations { funcs, entities })
     }
+    /// Checks the argument names of every RPC call site against the [`ServiceMetadata`](crate::runtime::ServiceMetadata) provided by `system`
+    /// (see [`ServiceMetadata::check_arg_names`](crate::runtime::ServiceMetadata::check_arg_names)).
+    /// Calls to services for which the system has no metadata are not checked.
+    ///
+    /// Returns the bytecode position and error for each failed check.
+    /// Argument values can only be checked during execution, which is done automatically for every RPC call.
+    pub fn check_rpcs<S: System>(&self, system: &S) -> Vec<(usize, RpcCheckError)> {
+        let mut res = vec![];
+        let mut meta_stack = vec![];
+        let mut pos = 0;
+        while pos < self.code.len() {
+            let (ins, aft) = Instruction::read(&self.code, &self.data, pos);
+            match ins {
+                Instruction::MetaPush { value } => meta_stack.push(value),
+                Instruction::Call { params, .. } => { meta_stack.drain(meta_stack.len() - params..); }
+                Instruction::MakeClosure { params, captures, .. } => { meta_stack.drain(meta_stack.len() - (params + captures)..); }
+                Instruction::CallRpc { service, rpc, args } => {
+                    let arg_names: Vec<_> = meta_stack.drain(meta_stack.len() - args..).collect();
+                    if let Some(metadata) = system.service_metadata(service) {
+                        if let Err(e) = metadata.check_arg_names(service, rpc, &arg_names) {
+                            res.push((pos, e));
+                        }
+                    }
+                }
+                _ => (),
+            }
+            pos = aft;
+        }
+        res
+    }
     /// Generates a hex dump of the stored code, including instructions and addresses.
     pub fn dump_code(&self, f: &mut dyn Write) -> io::Result<()> {
         let mut pos = 0;
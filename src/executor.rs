@@ -0,0 +1,286 @@
+//! A high-level, ready-to-use driver for running a [`Project`].
+//!
+//! The lower-level [`Project`] type must live inside of a garbage collected arena, which requires some boilerplate to set up,
+//! and which must be periodically released from [`ExecutorArena::mutate`] for garbage collection to actually take place.
+//! [`Executor`] takes care of all of this and exposes a simple interface for feeding input and running the project.
+
+use std::prelude::v1::*;
+
+use derive_builder::Builder;
+
+use crate::*;
+use crate::gc::*;
+use crate::runtime::*;
+use crate::process::*;
+use crate::project::*;
+
+/// The root object stored in an [`ExecutorArena`].
+#[derive(Collect)]
+#[collect(no_drop)]
+pub struct Env<'gc, S: System> {
+    pub proj: GcCell<'gc, Project<'gc, S>>,
+}
+
+/// A garbage collected arena which holds an [`Env`] for use by an [`Executor`].
+///
+/// Due to limitations of `gc_arena`, arena types cannot be generic over the [`System`] type,
+/// so this trait should be implemented with the [`make_executor_arena`] macro rather than by hand.
+pub trait ExecutorArena: Sized {
+    /// The [`System`] type used by projects stored in this arena.
+    type System: System;
+
+    /// Creates a new arena with the root object returned by `f`.
+    fn new<F>(params: ArenaParameters, f: F) -> Self where F: for<'gc> FnOnce(MutationContext<'gc, '_>) -> Env<'gc, Self::System>;
+    /// Gives mutable access to the root object. Garbage collection cannot happen until this returns.
+    fn mutate<F, R>(&mut self, f: F) -> R where F: for<'gc> FnOnce(MutationContext<'gc, '_>, &Env<'gc, Self::System>) -> R;
+
+    /// The total number of bytes currently allocated by the arena.
+    /// This only includes the garbage collected objects themselves, not any heap storage they own (such as the contents of lists and strings).
+    fn total_allocated(&self) -> usize;
+    /// The amount of allocation debt (in bytes) that has accumulated since the last collection.
+    fn allocation_debt(&self) -> f64;
+    /// Performs incremental garbage collection until the allocation debt has been paid off.
+    fn collect_debt(&mut self);
+    /// Runs a full garbage collection cycle.
+    fn collect_all(&mut self);
+}
+
+/// Defines a new [`ExecutorArena`] type for the given [`System`] type.
+///
+/// The first argument is the (optionally `pub`) name of the new arena type, and the second is the name of a
+/// type alias which will be defined for its root object (an [`Env`] specialized to the given [`System`]).
+///
+/// ```ignore
+/// make_executor_arena!(pub MyArena, MyEnv, MySystem);
+/// let executor = Executor::<MyArena>::new(&role, settings, executor_settings, system);
+/// ```
+#[macro_export]
+macro_rules! make_executor_arena {
+    ($vis:vis $arena:ident, $root:ident, $system:ty) => {
+        $vis type $root<'gc> = $crate::executor::Env<'gc, $system>;
+        $crate::gc::make_arena!(@impl $vis $arena, $root);
+
+        impl $crate::executor::ExecutorArena for $arena {
+            type System = $system;
+
+            fn new<F>(params: $crate::gc::ArenaParameters, f: F) -> Self where F: for<'gc> FnOnce($crate::gc::MutationContext<'gc, '_>) -> $root<'gc> {
+                $arena::new(params, f)
+            }
+            fn mutate<F, R>(&mut self, f: F) -> R where F: for<'gc> FnOnce($crate::gc::MutationContext<'gc, '_>, &$root<'gc>) -> R {
+                $arena::mutate(self, f)
+            }
+
+            fn total_allocated(&self) -> usize { $arena::total_allocated(self) }
+            fn allocation_debt(&self) -> f64 { $arena::allocation_debt(self) }
+            fn collect_debt(&mut self) { $arena::collect_debt(self) }
+            fn collect_all(&mut self) { $arena::collect_all(self) }
+        }
+    };
+}
+
+/// Settings to use for an [`Executor`].
+#[derive(Builder, Clone)]
+#[builder(no_std)]
+pub struct ExecutorSettings {
+    /// The maximum number of project steps to perform in a single call to [`Executor::step`] (default `1024`).
+    /// Garbage collection can only be performed between batches, so larger values trade memory usage for throughput.
+    #[builder(default = "1024")]
+    batch_size: usize,
+    /// The minimum amount of allocation debt (in bytes) before garbage collection is performed between batches (default `0.0`).
+    #[builder(default = "0.0")]
+    gc_debt_threshold: f64,
+}
+
+/// A high-level driver which owns a garbage collected arena, the [`Project`] stored inside it, and the [`System`] used to run it.
+pub struct Executor<A: ExecutorArena> {
+    arena: A,
+    system: A::System,
+    settings: ExecutorSettings,
+}
+impl<A: ExecutorArena> Executor<A> {
+    /// Creates a new executor for the given role.
+    /// Note that this does not start the project; use [`Executor::input`] with [`Input::Start`] to do so.
+    pub fn new(role: &ast::Role, settings: Settings, executor_settings: ExecutorSettings, system: A::System) -> Self {
+        let arena = A::new(Default::default(), |mc| {
+            let proj = Project::from_ast(mc, role, settings);
+            Env { proj: GcCell::allocate(mc, proj) }
+        });
+        Self { arena, system, settings: executor_settings }
+    }
+    /// Gets a reference to the [`System`] used by this executor.
+    pub fn system(&self) -> &A::System {
+        &self.system
+    }
+    /// Checks all RPC call sites in the project against the metadata provided by the [`System`] (see [`ByteCode::check_rpcs`](crate::bytecode::ByteCode::check_rpcs)).
+    pub fn check_rpcs(&mut self) -> Vec<(usize, RpcCheckError)> {
+        let system = &self.system;
+        self.arena.mutate(|_, env| env.proj.read().check_rpcs(system))
+    }
+    /// Feeds input to the project (see [`Project::input`]).
+    pub fn input(&mut self, input: Input) {
+        self.arena.mutate(|mc, env| env.proj.write(mc).input(input));
+    }
+    /// Executes a single batch of at most [`ExecutorSettings::batch_size`] project steps, then performs garbage collection if needed.
+    /// After a collection, [`GlobalContext::heap_usage`] is reset to the size of the data which is still reachable (see [`Project::heap_size`]).
+    ///
+    /// If the batch ends early due to an error or the project becoming idle or blocked, that result is returned; otherwise [`ProjectStep::Normal`] is returned.
+    pub fn step(&mut self) -> ProjectStep {
+        let (batch_size, system) = (self.settings.batch_size, &self.system);
+        let res = self.arena.mutate(|mc, env| {
+            let mut proj = env.proj.write(mc);
+            for _ in 0..batch_size {
+                match proj.step(mc, system) {
+                    ProjectStep::Normal => (),
+                    x => return x,
+                }
+            }
+            ProjectStep::Normal
+        });
+
+        if self.arena.allocation_debt() > self.settings.gc_debt_threshold {
+            self.arena.collect_debt();
+            self.arena.mutate(|mc, env| {
+                let proj = env.proj.read();
+                let heap_size = proj.heap_size();
+                proj.global_context().write(mc).heap_usage = heap_size;
+            });
+        }
+
+        res
+    }
+    /// Runs the project until there are no more running processes.
+    ///
+    /// Errors are not fatal to the project as a whole, so execution continues after an error.
+    /// Each error is passed to `on_error` as soon as it occurs.
+    ///
+    /// With the [`std`](crate) feature, the current thread is blocked whenever the project is waiting (see [`Executor::wait`]).
+    /// Otherwise, the project is simply stepped again.
+    pub fn run<F: FnMut(ExecError)>(&mut self, mut on_error: F) {
+        loop {
+            match self.step() {
+                ProjectStep::Normal => (),
+                ProjectStep::Idle => return,
+                ProjectStep::Error { error } => on_error(error),
+                ProjectStep::Waiting { info: _info } => {
+                    #[cfg(any(test, feature = "std"))]
+                    self.wait(&_info);
+                }
+            }
+        }
+    }
+    /// Blocks the current thread until the project might be able to make progress after returning [`ProjectStep::Waiting`] with the given `info`.
+    /// That is, until the earliest timer expires, the [`System`] signals that an asynchronous operation has completed (see [`System::register_waker`]),
+    /// or one of the awaited barriers is released (see [`BarrierCondition::register_waker`]).
+    /// Spurious wakeups are possible.
+    #[cfg(any(test, feature = "std"))]
+    pub fn wait(&self, info: &WaitInfo) {
+        extern crate std as real_std;
+        use real_std::sync::Arc;
+        use real_std::task::Wake;
+        use real_std::thread::{self, Thread};
+        use real_std::time::Duration;
+
+        struct ThreadWaker(Thread);
+        impl Wake for ThreadWaker {
+            fn wake(self: Arc<Self>) { self.0.unpark() }
+        }
+
+        let waker = Arc::new(ThreadWaker(thread::current())).into();
+        if info.rpcs != 0 { self.system.register_waker(&waker) }
+        for condition in info.conditions.iter() {
+            condition.register_waker(&waker);
+        }
+        match info.wake_time {
+            // if the time is unavailable, the sleeping processes will fail with the same error when stepped
+            Some(wake_time) => if let Ok(now) = self.system.time_ms() {
+                if wake_time > now { thread::park_timeout(Duration::from_millis(wake_time - now)) }
+            }
+            None => if info.rpcs != 0 || !info.conditions.is_empty() { thread::park() }
+        }
+    }
+    /// Asynchronously runs the project until there are no more running processes.
+    /// This is equivalent to [`Executor::run`], except that it yields to the async runtime between batches
+    /// and awaits timers and asynchronous operations (see [`Executor::wait_async`]) rather than blocking the current thread.
+    ///
+    /// [`Executor`] is not [`Send`], because the garbage collected arena and the [`Settings`] of the project cannot leave the thread that created them.
+    /// This means that it cannot be run with `tokio::spawn`; instead, many executors can share a single thread by running them with
+    /// `tokio::task::spawn_local` on a `tokio::task::LocalSet`. The [`System`] may still be [`Send`] (as [`StdSystem`] is) and created elsewhere.
+    #[cfg(any(test, feature = "std"))]
+    pub async fn run_async<F: FnMut(ExecError)>(&mut self, mut on_error: F) {
+        loop {
+            match self.step() {
+                ProjectStep::Normal => tokio::task::yield_now().await,
+                ProjectStep::Idle => return,
+                ProjectStep::Error { error } => on_error(error),
+                ProjectStep::Waiting { info } => self.wait_async(&info).await,
+            }
+        }
+    }
+    /// The async equivalent of [`Executor::wait`], which completes when the project might be able to make progress.
+    /// This must be awaited from within a tokio runtime.
+    #[cfg(any(test, feature = "std"))]
+    pub async fn wait_async(&self, info: &WaitInfo) {
+        extern crate std as real_std;
+        use real_std::future::{Future, pending};
+        use real_std::pin::Pin;
+        use real_std::task::{Context, Poll};
+        use real_std::time::Duration;
+
+        struct Wakeup<'a, S: System> {
+            system: &'a S,
+            info: &'a WaitInfo,
+            registered: bool,
+        }
+        impl<S: System> Future for Wakeup<'_, S> {
+            type Output = ();
+            fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+                let this = self.get_mut();
+                if this.registered { return Poll::Ready(()) }
+                if this.info.rpcs != 0 { this.system.register_waker(cx.waker()) }
+                for condition in this.info.conditions.iter() {
+                    condition.register_waker(cx.waker());
+                }
+                this.registered = true;
+                Poll::Pending
+            }
+        }
+
+        let wakeup = async {
+            match info.rpcs != 0 || !info.conditions.is_empty() {
+                true => Wakeup { system: &self.system, info, registered: false }.await,
+                false => pending().await,
+            }
+        };
+        match (info.wake_time, self.system.time_ms()) {
+            (Some(wake_time), Ok(now)) => { let _ = tokio::time::timeout(Duration::from_millis(wake_time.saturating_sub(now)), wakeup).await; }
+            (Some(_), Err(_)) => tokio::task::yield_now().await,
+            (None, _) => wakeup.await,
+        }
+    }
+    /// Gives temporary access to the underlying [`Project`], e.g., to inspect or modify its state.
+    /// Garbage collection cannot happen during this call.
+    pub fn mutate<F, R>(&mut self, f: F) -> R where F: for<'gc> FnOnce(MutationContext<'gc, '_>, &mut Project<'gc, A::System>) -> R {
+        self.arena.mutate(|mc, env| f(mc, &mut *env.proj.write(mc)))
+    }
+    /// The total number of bytes currently allocated by the underlying arena (see [`ExecutorArena::total_allocated`]).
+    /// This is not the measure used by the heap size limit in [`Settings`], which also counts the contents of lists and strings.
+    pub fn total_allocated(&self) -> usize {
+        self.arena.total_allocated()
+    }
+    /// Runs a full garbage collection cycle.
+    pub fn collect_all(&mut self) {
+        self.arena.collect_all();
+    }
+}
+
+#[cfg(any(test, feature = "std"))]
+make_executor_arena!(pub StdArena, StdEnv, StdSystem);
+
+/// An [`Executor`] which uses the [`StdSystem`] implementation of [`System`].
+#[cfg(any(test, feature = "std"))]
+pub type StdExecutor = Executor<StdArena>;
+
+make_executor_arena!(pub MockArena, MockEnv, MockSystem);
+
+/// An [`Executor`] which uses the [`MockSystem`] implementation of [`System`].
+pub type MockExecutor = Executor<MockArena>;
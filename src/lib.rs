@@ -33,7 +33,7 @@ extern crate no_std_compat as std;
 
 /// Re-exports of relevant items from `gc_arena`.
 pub mod gc {
-    pub use gc_arena::{Collect, Gc, GcCell, MutationContext, make_arena};
+    pub use gc_arena::{Collect, Gc, GcCell, MutationContext, ArenaParameters, make_arena};
 }
 
 /// Re-exports of relevant items from `serde_json`.
@@ -49,5 +49,6 @@ pub mod slotmap;
 pub mod runtime;
 pub mod process;
 pub mod project;
+pub mod executor;
 
 #[cfg(test)] mod test;
@@ -5,11 +5,13 @@ use std::io::Read;
 use clap::Parser;
 
 use netsblox_vm::*;
-use netsblox_vm::gc::{GcCell, Collect, make_arena};
 use netsblox_vm::bytecode::*;
 use netsblox_vm::runtime::*;
 use netsblox_vm::process::*;
 use netsblox_vm::project::*;
+use netsblox_vm::executor::*;
+
+make_executor_arena!(CliArena, CliEnv, RoutingSystem<StdSystem>);
 
 macro_rules! crash {
     ($ret:literal : $($tt:tt)*) => {{
@@ -18,13 +20,6 @@ macro_rules! crash {
     }}
 }
 
-#[derive(Collect)]
-#[collect(no_drop)]
-struct Env<'gc> {
-    proj: GcCell<'gc, Project<'gc, StdSystem>>,
-}
-make_arena!(EnvArena, Env);
-
 #[derive(Parser, Debug)]
 enum Mode {
     Run {
@@ -33,6 +28,22 @@ enum Mode {
 
         #[clap(long, default_value_t = String::from("https://editor.netsblox.org"))]
         server: String,
+
+        /// A JSON file of service descriptions used to check RPC calls
+        #[clap(long)] rpc_metadata: Option<String>,
+        /// Fetch service descriptions from the server to check RPC calls
+        #[clap(long)] fetch_rpc_metadata: bool,
+        /// A directory in which to keep cloud variables, rather than on the server
+        #[clap(long)] storage: Option<String>,
+        /// A directory which the project is allowed to read and write files in (with the Files service)
+        #[clap(long)] files: Option<String>,
+
+        /// Only allow calling the given service or rpc (e.g., "PhoneIoT" or "PhoneIoT.getColor")
+        #[clap(long)] allow_rpc: Vec<String>,
+        /// Never allow calling the given service or rpc (e.g., "PhoneIoT" or "PhoneIoT.getColor")
+        #[clap(long)] deny_rpc: Vec<String>,
+        /// Limit the rate of calls to a service (e.g., "PhoneIoT=10/1000" for at most 10 calls per second)
+        #[clap(long)] rpc_rate_limit: Vec<String>,
     },
     Dump {
         src: String,
@@ -67,31 +78,62 @@ fn open_project(src: &str, role: Option<&str>) -> (String, ast::Role) {
     (parsed.name, role)
 }
 
+fn split_rpc_rule(rule: &str) -> (&str, Option<&str>) {
+    match rule.split_once('.') {
+        Some((service, rpc)) => (service, Some(rpc)),
+        None => (rule, None),
+    }
+}
+
 fn main() {
     match Mode::parse() {
-        Mode::Run { src, role, server } => {
+        Mode::Run { src, role, server, rpc_metadata, fetch_rpc_metadata, storage, files, allow_rpc, deny_rpc, rpc_rate_limit } => {
             let (project_name, role) = open_project(&src, role.as_deref());
 
-            let mut env = EnvArena::new(Default::default(), |mc| {
-                let settings = SettingsBuilder::default()
-                    .printer(Rc::new(|value, entity| if let Some(value) = value { println!("{:?} > {:?}", entity, value) }))
-                    .build().unwrap();
-
-                let mut proj = Project::from_ast(mc, &role, settings);
-                proj.input(Input::Start);
-                Env { proj: GcCell::allocate(mc, proj) }
-            });
-            let system = StdSystem::new(server, Some(&project_name));
+            let mut permissions = RpcPermissions::new();
+            for rule in allow_rpc.iter() {
+                let (service, rpc) = split_rpc_rule(rule);
+                permissions = permissions.allow(service, rpc);
+            }
+            for rule in deny_rpc.iter() {
+                let (service, rpc) = split_rpc_rule(rule);
+                permissions = permissions.deny(service, rpc);
+            }
+            for rule in rpc_rate_limit.iter() {
+                let limit = rule.split_once('=').and_then(|(service, limit)| limit.split_once('/').map(|(calls, period)| (service, calls.parse().ok(), period.parse().ok())));
+                match limit {
+                    Some((service, Some(calls), Some(period))) => permissions = permissions.rate_limit(service, calls, period),
+                    _ => crash!(8: "invalid rpc rate limit '{rule}' (expected SERVICE=CALLS/MILLISECONDS)"),
+                }
+            }
+            let has_permissions = !(allow_rpc.is_empty() && deny_rpc.is_empty() && rpc_rate_limit.is_empty());
 
-            env.mutate(|mc, env| {
-                let mut proj = env.proj.write(mc);
-                loop {
-                    match proj.step(mc, &system) {
-                        ProjectStep::Idle => return,
-                        ProjectStep::Normal => (),
-                    }
+            let settings = SettingsBuilder::default()
+                .rpc_permissions(if has_permissions { Some(Rc::new(permissions)) } else { None })
+                .printer(Rc::new(|value, entity| if let Some(value) = value { println!("{:?} > {:?}", entity, value) }))
+                .build().unwrap();
+            let service_metadata = match rpc_metadata {
+                Some(path) => match ServiceMetadata::load_all(&path) {
+                    Ok(x) => x,
+                    Err(e) => crash!(7: "failed to load rpc metadata from '{path}':\n{e:?}"),
                 }
-            });
+                None => Default::default(),
+            };
+            let config = StdSystemConfigBuilder::default().base_url(server).project_name(Some(project_name))
+                .service_metadata(service_metadata).fetch_service_metadata(fetch_rpc_metadata).storage_dir(storage.clone()).file_root(files.clone()).build().unwrap();
+            let system = match StdSystem::new(config) {
+                Ok(x) => RoutingSystem::new(x),
+                Err(e) => crash!(6: "failed to initialize system:\n{e:?}"),
+            };
+            let system = if storage.is_some() { system.with_cloud_variables() } else { system };
+            let system = if files.is_some() { system.with_files() } else { system };
+
+            let mut executor = Executor::<CliArena>::new(&role, settings, ExecutorSettingsBuilder::default().build().unwrap(), system);
+            for (pos, error) in executor.check_rpcs() {
+                eprintln!("warning: {error:?} (at {pos})");
+            }
+            executor.input(Input::Start);
+            executor.run(|error| eprintln!("error: {error:?}"));
         }
         Mode::Dump { src, role } => {
             let (_, role) = open_project(&src, role.as_deref());
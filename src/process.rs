@@ -10,7 +10,9 @@ use std::prelude::v1::*;
 use std::collections::{BTreeMap, BTreeSet};
 use std::borrow::Cow;
 use std::rc::Rc;
+use std::cell::RefCell;
 use std::iter;
+use std::mem;
 
 use derive_builder::Builder;
 
@@ -44,6 +46,8 @@ pub enum ErrorCause {
     CallDepthLimit { limit: usize },
     /// Attempt to call a closure which required `expected` arguments, but `got` arguments were supplied.
     ClosureArgCount { expected: usize, got: usize },
+    /// Attempt to use a closure with `got` parameters in a higher-order list operation (such as map or combine), which passes between `min` and `max` arguments.
+    HofArgCount { min: usize, max: usize, got: usize },
     /// An operation resulted in an error generated by the [`System`].
     SystemError { error: SystemError },
     /// Attempt to pass a non-simple value to external code, such as a system call or RPC call.
@@ -59,11 +63,26 @@ pub enum ErrorCause {
     JsonHadBadNumber { value: String },
     /// Attempt to interpret an invalid unicode code point (number) as a character.
     InvalidUnicode { value: f64 },
+    /// Attempt to create a list with `size` items, which exceeds the maximum list size, `limit`. This can be configured by [`Settings`].
+    ListSizeLimit { size: usize, limit: usize },
+    /// Attempt to create a string of `size` bytes, which exceeds the maximum string size, `limit`. This can be configured by [`Settings`].
+    StringSizeLimit { size: usize, limit: usize },
+    /// The (approximate) total number of bytes allocated by the program, `size`, exceeded the maximum heap size, `limit`.
+    /// This can be configured by [`Settings`] (see also [`GlobalContext::heap_usage`]).
+    HeapSizeLimit { size: usize, limit: usize },
+    /// An RPC call did not match the [`ServiceMetadata`] provided by the [`System`] (see [`System::service_metadata`]).
+    RpcCheck { error: RpcCheckError },
+    /// Entry `index` (1-based) of the argument list of a dynamic RPC call was not a `[name, value]` pair.
+    RpcArgNotPair { index: usize },
+    /// An RPC call was rejected by the [`RpcPermissions`] in the [`Settings`].
+    RpcPermission { error: RpcPermissionError },
 }
 impl From<ConversionError> for ErrorCause { fn from(e: ConversionError) -> Self { Self::ConversionError { got: e.got, expected: e.expected } } }
 impl From<SystemError> for ErrorCause { fn from(error: SystemError) -> Self { Self::SystemError { error } } }
 impl From<SimplifyError> for ErrorCause { fn from(error: SimplifyError) -> Self { Self::SimplifyError { error } } }
 impl From<ToJsonError> for ErrorCause { fn from(error: ToJsonError) -> Self { Self::ToJsonError { error } } }
+impl From<RpcCheckError> for ErrorCause { fn from(error: RpcCheckError) -> Self { Self::RpcCheck { error } } }
+impl From<RpcPermissionError> for ErrorCause { fn from(error: RpcPermissionError) -> Self { Self::RpcPermission { error } } }
 
 /// Result of stepping through a [`Process`].
 pub enum ProcessStep<'gc> {
@@ -82,6 +101,21 @@ pub enum ProcessStep<'gc> {
     Terminate { result: Option<Value<'gc>> },
     /// The process has requested to broadcast a message to all entities, which may trigger other code to execute.
     Broadcast { msg_type: Gc<'gc, String>, barrier: Option<Barrier> },
+    /// The process is blocked on an asynchronous operation and made no progress.
+    /// This should be handled like [`ProcessStep::Yield`], except that stepping the process again is pointless until the operation might have completed.
+    Waiting { reason: WaitReason },
+}
+
+/// The reason that a [`Process`] is blocked (see [`ProcessStep::Waiting`]).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WaitReason {
+    /// Awaiting the result of an RPC, which will be signaled by the [`System`] (see [`System::register_waker`]).
+    /// If the RPC has a timeout (see [`Settings`]), `deadline` is the time at which it expires.
+    Rpc { deadline: Option<u64> },
+    /// Sleeping until [`System::time_ms`] reaches `wake_time`.
+    Timer { wake_time: u64 },
+    /// Awaiting the completion of a [`Barrier`], which requires other processes to make progress.
+    Barrier { condition: BarrierCondition },
 }
 
 /// Settings to use for a [`Process`].
@@ -93,6 +127,31 @@ pub struct Settings {
     #[builder(default = "1024")]
     max_call_depth: usize,
 
+    /// The maximum number of items in a list, or [`None`] for no limit (default [`None`]).
+    #[builder(default = "None")]
+    max_list_size: Option<usize>,
+    /// The maximum size of a string in bytes, or [`None`] for no limit (default [`None`]).
+    #[builder(default = "None")]
+    max_string_size: Option<usize>,
+    /// The maximum approximate number of bytes that the program may allocate, or [`None`] for no limit (default [`None`]).
+    /// This is tracked by [`GlobalContext::heap_usage`], which must be reset to the size of the reachable data after garbage collection (see [`Project::heap_size`](crate::project::Project::heap_size)).
+    /// [`Executor`](crate::executor::Executor) does this automatically; hosts which step a [`Project`](crate::project::Project) directly must do so themselves,
+    /// or programs with constant memory use will eventually exceed the limit.
+    #[builder(default = "None")]
+    max_heap_size: Option<usize>,
+
+    /// The maximum time (in the units of [`System::time_ms`]) to wait for the result of an RPC, or [`None`] for no limit (default [`None`]).
+    /// When an RPC times out, it is cancelled (see [`System::cancel_rpc`]) and the RPC fails with an error message, as if returned by the server.
+    #[builder(default = "None")]
+    rpc_timeout: Option<u64>,
+    /// Restrictions on which RPCs may be called, or [`None`] to allow all RPCs (default [`None`]).
+    /// Calls which are not permitted fail with [`ErrorCause::RpcPermission`] and are never sent to the [`System`].
+    #[builder(default = "None")]
+    rpc_permissions: Option<Rc<RpcPermissions>>,
+    /// Decides which lists are sent as JSON objects rather than arrays, both in RPC arguments and in [`Instruction::ListJson`] (default [`JsonObjectStrategy::TextKeys`]).
+    #[builder(default = "JsonObjectStrategy::TextKeys")]
+    json_objects: JsonObjectStrategy,
+
     /// A function used to process all "say" and "think" blocks.
     /// The first argument is the actual message value, or [`None`] to clear the output (Snap!-style).
     /// The second argument is a reference to the entity making the request.
@@ -109,11 +168,169 @@ struct ReturnPoint {
     value_stack_size: usize,
 }
 
+#[derive(Collect)]
+#[collect(no_drop)]
+struct HofState<'gc> {
+    pos: usize,
+    call_depth: usize,
+    op: HofOp,
+    closure: GcCell<'gc, Closure<'gc>>,
+    list: Value<'gc>,
+    items: Vec<Value<'gc>>,
+    index: usize,
+    acc: Vec<Value<'gc>>,
+    search: (usize, usize),
+    args: usize,
+}
+impl<'gc> HofState<'gc> {
+    fn new(pos: usize, call_depth: usize, op: HofOp, closure: GcCell<'gc, Closure<'gc>>, list: Value<'gc>) -> Result<Self, ErrorCause> {
+        let items = list.as_list()?.read().clone();
+
+        let (min_args, max_args) = match op {
+            HofOp::Map | HofOp::Keep | HofOp::Find => (1, 3),
+            HofOp::Combine => (2, 4),
+            HofOp::Sort => (2, 2),
+        };
+        // closures with empty slots only get the minimum arguments, which fill the slots as usual (see [`Instruction::MakeClosure`])
+        let args = match (closure.read().params.len(), closure.read().empty_slots) {
+            (0, slots) if slots > 0 => min_args,
+            (params, _) if params < min_args || params > max_args => return Err(ErrorCause::HofArgCount { min: min_args, max: max_args, got: params }),
+            (params, _) => params,
+        };
+
+        let (index, acc) = match (op, items.first()) {
+            (HofOp::Combine, Some(first)) => (1, vec![*first]),
+            (HofOp::Combine, None) => (0, vec![0f64.into()]),
+            _ => (0, vec![]),
+        };
+        Ok(Self { pos, call_depth, op, closure, list, items, index, acc, search: (0, 0), args })
+    }
+    /// Records the result of the previous call to the closure (if any),
+    /// and returns the arguments for the next call, or [`None`] if the operation is complete (see [`HofState::finish`]).
+    fn advance(&mut self, res: Option<Value<'gc>>) -> Result<Option<Vec<Value<'gc>>>, ErrorCause> {
+        if let Some(res) = res {
+            match self.op {
+                HofOp::Map => self.acc.push(res),
+                HofOp::Keep => if res.to_bool()? { self.acc.push(self.items[self.index]) }
+                HofOp::Find => if res.to_bool()? {
+                    self.acc.push(self.items[self.index]);
+                    return Ok(None);
+                }
+                HofOp::Combine => self.acc[0] = res,
+                HofOp::Sort => {
+                    // binary insertion sort: the closure tells us if the item goes before the middle of the search range
+                    let mid = (self.search.0 + self.search.1) / 2;
+                    self.search = if res.to_bool()? { (self.search.0, mid) } else { (mid + 1, self.search.1) };
+                }
+            }
+            if !matches!(self.op, HofOp::Sort) { self.index += 1 }
+        }
+
+        if let HofOp::Sort = self.op {
+            while self.index < self.items.len() {
+                let (lo, hi) = self.search;
+                if lo < hi { return Ok(Some(vec![self.items[self.index], self.acc[(lo + hi) / 2]])) }
+                self.acc.insert(lo, self.items[self.index]);
+                self.index += 1;
+                self.search = (0, self.acc.len());
+            }
+            return Ok(None);
+        }
+
+        if self.index >= self.items.len() { return Ok(None) }
+        let mut args = Vec::with_capacity(4);
+        if let HofOp::Combine = self.op { args.push(self.acc[0]) }
+        args.extend([self.items[self.index], ((self.index + 1) as f64).into(), self.list]);
+        args.truncate(self.args);
+        Ok(Some(args))
+    }
+    fn finish(self, mc: MutationContext<'gc, '_>) -> Value<'gc> {
+        match self.op {
+            HofOp::Map | HofOp::Keep | HofOp::Sort => GcCell::allocate(mc, self.acc).into(),
+            HofOp::Find => self.acc.first().copied().unwrap_or_else(|| Gc::allocate(mc, String::new()).into()),
+            HofOp::Combine => self.acc[0],
+        }
+    }
+}
+
+/// A queue of RPCs which were started by a [`Process`] but are no longer awaited, and should be cancelled (see [`System::cancel_rpc`]).
+///
+/// Keys are added when a process is stopped, re-initialized, or dropped while awaiting an RPC.
+/// A process cancels the keys in its queue on the next call to [`Process::step`], so a handle to the queue (see [`Process::orphaned_rpcs`])
+/// is only needed to cancel the RPCs of a process that will not be stepped again.
+#[derive(Collect)]
+#[collect(require_static)]
+pub struct OrphanedRpcs<S: System>(Rc<RefCell<Vec<S::RpcKey>>>);
+impl<S: System> Clone for OrphanedRpcs<S> {
+    fn clone(&self) -> Self {
+        Self(self.0.clone())
+    }
+}
+impl<S: System> Default for OrphanedRpcs<S> {
+    fn default() -> Self {
+        Self(Default::default())
+    }
+}
+impl<S: System> OrphanedRpcs<S> {
+    /// Removes and returns all the keys currently in the queue.
+    pub fn take(&self) -> Vec<S::RpcKey> {
+        mem::take(&mut *self.0.borrow_mut())
+    }
+    /// Cancels all the RPCs currently in the queue.
+    pub fn cancel_all(&self, system: &S) {
+        for key in self.take() {
+            system.cancel_rpc(&key);
+        }
+    }
+}
+
+/// The key of an awaited RPC, which is added to [`OrphanedRpcs`] if dropped before being released.
+#[derive(Collect)]
+#[collect(require_static)]
+struct PendingRpc<S: System> {
+    key: Option<S::RpcKey>,
+    orphans: OrphanedRpcs<S>,
+}
+impl<S: System> PendingRpc<S> {
+    fn key(&self) -> &S::RpcKey {
+        self.key.as_ref().unwrap()
+    }
+    /// Marks the RPC as no longer needing to be cancelled, e.g., because it completed.
+    fn release(&mut self) {
+        self.key = None;
+    }
+}
+impl<S: System> Drop for PendingRpc<S> {
+    fn drop(&mut self) {
+        if let Some(key) = self.key.take() {
+            self.orphans.0.borrow_mut().push(key);
+        }
+    }
+}
+
 #[derive(Collect)]
 #[collect(require_static)]
 enum Defer<S: System> {
-    RpcResult { key: S::RpcKey, aft_pos: usize },
+    RpcResult { rpc: PendingRpc<S>, aft_pos: usize, deadline: Option<u64> },
     Barrier { condition: BarrierCondition, aft_pos: usize },
+    Sleep { wake_time: u64, aft_pos: usize },
+}
+impl<S: System> Defer<S> {
+    /// Checks and sends an RPC request, producing the deferred action which awaits its result.
+    fn call_rpc(system: &S, settings: &Settings, orphans: &OrphanedRpcs<S>, service: String, rpc: String, args: Vec<(String, Json)>, aft_pos: usize) -> Result<Self, ErrorCause> {
+        if let Some(permissions) = &settings.rpc_permissions {
+            permissions.check(&service, &rpc, || system.time_ms())??;
+        }
+        if let Some(metadata) = system.service_metadata(&service) {
+            metadata.check_call(&service, &rpc, &args)?;
+        }
+        let key = system.call_rpc(service, rpc, args)?;
+        let deadline = match settings.rpc_timeout {
+            Some(timeout) => Some(system.time_ms()?.saturating_add(timeout)),
+            None => None,
+        };
+        Ok(Defer::RpcResult { rpc: PendingRpc { key: Some(key), orphans: orphans.clone() }, aft_pos, deadline })
+    }
 }
 
 /// A [`ByteCode`] execution primitive.
@@ -133,9 +350,11 @@ pub struct Process<'gc, S: System> {
     barrier: Option<Barrier>,
     warp_counter: usize,
     call_stack: Vec<(ReturnPoint, SymbolTable<'gc>)>, // tuples of (ret pos, locals)
+    hof_stack: Vec<HofState<'gc>>,
     value_stack: Vec<Value<'gc>>,
     meta_stack: Vec<String>,
     defer: Option<Defer<S>>,
+    orphaned_rpcs: OrphanedRpcs<S>,
     last_rpc_error: Option<Value<'gc>>,
 }
 impl<'gc, S: System> Process<'gc, S> {
@@ -149,9 +368,11 @@ impl<'gc, S: System> Process<'gc, S> {
             pos: 0,
             warp_counter: 0,
             call_stack: vec![],
+            hof_stack: vec![],
             value_stack: vec![],
             meta_stack: vec![],
             defer: None,
+            orphaned_rpcs: Default::default(),
             last_rpc_error: None,
         }
     }
@@ -164,6 +385,7 @@ impl<'gc, S: System> Process<'gc, S> {
     /// A [`Barrier`] may also be set, which will be destroyed upon termination, either due to completion or an error.
     /// 
     /// Any previous process state is wiped when performing this action.
+    /// If the process was awaiting an RPC, it will be cancelled on the next call to [`Process::step`] (see [`System::cancel_rpc`]).
     pub fn initialize(&mut self, locals: SymbolTable<'gc>, barrier: Option<Barrier>) {
         self.pos = self.start_pos;
         self.running = true;
@@ -171,17 +393,56 @@ impl<'gc, S: System> Process<'gc, S> {
         self.warp_counter = 0;
         self.call_stack.clear();
         self.call_stack.push((ReturnPoint { pos: usize::MAX, warp_counter: 0, value_stack_size: 0 }, locals));
+        self.hof_stack.clear();
         self.value_stack.clear();
         self.meta_stack.clear();
         self.defer = None;
         self.last_rpc_error = None;
     }
+    /// Stops the process, which transitions to the idle state (see [`Process::is_running`]).
+    /// If the process was awaiting an RPC, it will be cancelled on the next call to [`Process::step`] (see [`System::cancel_rpc`]).
+    pub fn stop(&mut self) {
+        if let Some(Defer::RpcResult { .. }) = &self.defer { self.defer = None }
+        self.running = false;
+        self.barrier = None;
+    }
+    /// Gets a handle to the queue of RPCs which this process started but is no longer awaiting.
+    /// Because a [`Process`] cannot access the [`System`] when it is dropped, the owner of a process which may be dropped while awaiting an RPC
+    /// should keep this handle and cancel the remaining RPCs afterwards (see [`OrphanedRpcs::cancel_all`]) to release any resources associated with them.
+    pub fn orphaned_rpcs(&self) -> OrphanedRpcs<S> {
+        self.orphaned_rpcs.clone()
+    }
+    /// Consumes the process and returns the keys of any RPCs which it started but is no longer awaiting.
+    /// This is equivalent to dropping the process and then taking the keys from [`Process::orphaned_rpcs`].
+    pub fn into_orphaned_rpcs(self) -> Vec<S::RpcKey> {
+        let orphans = self.orphaned_rpcs.clone();
+        drop(self);
+        orphans.take()
+    }
+    /// Counts all the values referenced by the process state, such as local variables and temporaries (see [`HeapCounter`]).
+    pub fn count_heap(&self, counter: &mut HeapCounter<'gc>) {
+        for (_, locals) in self.call_stack.iter() {
+            counter.add_symbols(locals);
+        }
+        for hof in self.hof_stack.iter() {
+            counter.add_value(&Value::Closure(hof.closure));
+            counter.add_value(&hof.list);
+            for value in hof.items.iter().chain(hof.acc.iter()) {
+                counter.add_value(value);
+            }
+        }
+        for value in self.value_stack.iter().chain(self.last_rpc_error.iter()) {
+            counter.add_value(value);
+        }
+    }
     /// Executes a single bytecode instruction.
     /// The return value can be used to determine what additional effects the script has requested,
     /// as well as to retrieve the return value or execution error in the event that the process terminates.
     /// 
     /// The process transitions to the idle state (see [`Process::is_running`]) upon failing with [`Err`] or succeeding with [`ProcessStep::Terminate`].
     pub fn step(&mut self, mc: MutationContext<'gc, '_>, system: &S) -> Result<ProcessStep<'gc>, ExecError> {
+        self.orphaned_rpcs.cancel_all(system);
+
         let res = self.step_impl(mc, system);
         if let Ok(ProcessStep::Terminate { .. }) | Err(_) = res {
             self.running = false;
@@ -190,14 +451,17 @@ impl<'gc, S: System> Process<'gc, S> {
         res.map_err(|cause| ExecError { cause, pos: self.pos })
     }
     fn step_impl(&mut self, mc: MutationContext<'gc, '_>, system: &S) -> Result<ProcessStep<'gc>, ErrorCause> {
-        match &self.defer {
+        match &mut self.defer {
             None => (),
-            Some(Defer::RpcResult { key, aft_pos }) => match system.poll_rpc(key)? {
+            Some(Defer::RpcResult { rpc, aft_pos, deadline }) => match system.poll_rpc(rpc.key())? {
                 AsyncPoll::Completed(x) => {
                     self.value_stack.push(match x {
                         Ok(x) => {
                             self.last_rpc_error = None;
-                            ops::json_to_value(mc, x, None)?
+                            match x {
+                                RpcResponse::Json(x) => ops::json_to_value(mc, x, None)?,
+                                RpcResponse::Blob(x) => Value::Blob(Gc::allocate(mc, x)),
+                            }
                         }
                         Err(x) => {
                             let x = Value::String(Gc::allocate(mc, x));
@@ -205,17 +469,36 @@ impl<'gc, S: System> Process<'gc, S> {
                             x
                         }
                     });
+                    rpc.release();
                     self.pos = *aft_pos;
                     self.defer = None;
                 }
-                AsyncPoll::Pending => return Ok(ProcessStep::Yield),
+                AsyncPoll::Pending => match deadline {
+                    Some(deadline) if system.time_ms()? >= *deadline => {
+                        system.cancel_rpc(rpc.key());
+                        rpc.release();
+                        let x = Value::String(Gc::allocate(mc, "RPC timed out".to_owned()));
+                        self.value_stack.push(x);
+                        self.last_rpc_error = Some(x);
+                        self.pos = *aft_pos;
+                        self.defer = None;
+                    }
+                    _ => return Ok(ProcessStep::Waiting { reason: WaitReason::Rpc { deadline: *deadline } }),
+                }
             }
             Some(Defer::Barrier { condition, aft_pos }) => match condition.is_completed() {
                 true => {
                     self.pos = *aft_pos;
                     self.defer = None;
                 }
-                false => return Ok(ProcessStep::Yield),
+                false => return Ok(ProcessStep::Waiting { reason: WaitReason::Barrier { condition: condition.clone() } }),
+            }
+            Some(Defer::Sleep { wake_time, aft_pos }) => match system.time_ms()? >= *wake_time {
+                true => {
+                    self.pos = *aft_pos;
+                    self.defer = None;
+                }
+                false => return Ok(ProcessStep::Waiting { reason: WaitReason::Timer { wake_time: *wake_time } }),
             }
         }
 
@@ -223,6 +506,7 @@ impl<'gc, S: System> Process<'gc, S> {
         if !entity.alive { return Ok(ProcessStep::Terminate { result: None }) }
 
         let mut global_context = self.global_context.write(mc);
+        let global_context = &mut *global_context;
         let mut context = [&mut global_context.globals, &mut entity.fields, &mut self.call_stack.last_mut().unwrap().1];
         let mut context = LookupGroup::new(&mut context);
 
@@ -286,11 +570,17 @@ impl<'gc, S: System> Process<'gc, S> {
             }
             Instruction::ShallowCopy => {
                 let val = self.value_stack.pop().unwrap();
+                if let Value::List(x) = &val {
+                    ops::track_heap(&self.settings, &mut global_context.heap_usage, x.read().len().saturating_mul(mem::size_of::<Value>()))?;
+                }
                 self.value_stack.push(val.shallow_copy(mc));
                 self.pos = aft_pos;
             }
 
             Instruction::MakeList { len } => {
+                ops::check_list_size(&self.settings, len)?;
+                ops::track_heap(&self.settings, &mut global_context.heap_usage, len * mem::size_of::<Value>())?;
+
                 let mut vals = Vec::with_capacity(len);
                 for _ in 0..len {
                     vals.push(self.value_stack.pop().unwrap());
@@ -305,6 +595,10 @@ impl<'gc, S: System> Process<'gc, S> {
 
                 let mut res = vec![];
                 if a.is_finite() && b.is_finite() {
+                    let len = (libm::floor(libm::fabs(b - a)) + 1.0) as usize;
+                    ops::check_list_size(&self.settings, len)?;
+                    ops::track_heap(&self.settings, &mut global_context.heap_usage, len.saturating_mul(mem::size_of::<Value>()))?;
+
                     if a <= b {
                         while a <= b {
                             res.push(a.into());
@@ -338,6 +632,8 @@ impl<'gc, S: System> Process<'gc, S> {
                 let index = self.value_stack.pop().unwrap();
                 let val = self.value_stack.pop().unwrap();
                 let mut list = list.write(mc);
+                ops::check_list_size(&self.settings, list.len() + 1)?;
+                ops::track_heap(&self.settings, &mut global_context.heap_usage, mem::size_of::<Value>())?;
 
                 let index = ops::prep_list_index(&index, list.len() + 1)?;
                 list.insert(index, val);
@@ -346,7 +642,10 @@ impl<'gc, S: System> Process<'gc, S> {
             Instruction::ListInsertLast => {
                 let list = self.value_stack.pop().unwrap().as_list()?;
                 let val = self.value_stack.pop().unwrap();
-                list.write(mc).push(val);
+                let mut list = list.write(mc);
+                ops::check_list_size(&self.settings, list.len() + 1)?;
+                ops::track_heap(&self.settings, &mut global_context.heap_usage, mem::size_of::<Value>())?;
+                list.push(val);
                 self.pos = aft_pos;
             }
             Instruction::ListInsertRandom => {
@@ -415,6 +714,84 @@ impl<'gc, S: System> Process<'gc, S> {
                 self.pos = aft_pos;
             }
 
+            Instruction::ListCat { count } => {
+                let lists = self.value_stack.drain(self.value_stack.len() - count..).map(|x| x.as_list()).collect::<Result<Vec<_>, _>>()?;
+                let len = lists.iter().map(|x| x.read().len()).sum();
+                ops::check_list_size(&self.settings, len)?;
+                ops::track_heap(&self.settings, &mut global_context.heap_usage, len.saturating_mul(mem::size_of::<Value>()))?;
+
+                let mut res = Vec::with_capacity(len);
+                for list in lists {
+                    res.extend(list.read().iter().copied());
+                }
+                self.value_stack.push(GcCell::allocate(mc, res).into());
+                self.pos = aft_pos;
+            }
+            Instruction::ListFind => {
+                let list = self.value_stack.pop().unwrap().as_list()?;
+                let value = self.value_stack.pop().unwrap();
+                let index = list.read().iter().position(|x| ops::check_eq(x, &value)).map(|x| x + 1).unwrap_or(0);
+                self.value_stack.push((index as f64).into());
+                self.pos = aft_pos;
+            }
+            Instruction::ListReverse | Instruction::ListSort => {
+                let mut res = self.value_stack.pop().unwrap().as_list()?.read().clone();
+                ops::track_heap(&self.settings, &mut global_context.heap_usage, res.len().saturating_mul(mem::size_of::<Value>()))?;
+                match ins {
+                    Instruction::ListReverse => res.reverse(),
+                    _ => ops::sort(mc, &mut res)?,
+                }
+                self.value_stack.push(GcCell::allocate(mc, res).into());
+                self.pos = aft_pos;
+            }
+            Instruction::ListFlatten | Instruction::ListDims | Instruction::ListColumns | Instruction::ListDistribution => {
+                let list = self.value_stack.pop().unwrap().as_list()?;
+                let res = match ins {
+                    Instruction::ListFlatten => GcCell::allocate(mc, ops::flatten(&self.settings, &list.into())?).into(),
+                    Instruction::ListDims => GcCell::allocate(mc, ops::dims(&list.into()).into_iter().map(|x| (x as f64).into()).collect::<Vec<_>>()).into(),
+                    Instruction::ListColumns => ops::columns(mc, list),
+                    _ => ops::distribution(mc, list),
+                };
+                ops::track_value(&self.settings, &mut global_context.heap_usage, &res)?;
+                self.value_stack.push(res);
+                self.pos = aft_pos;
+            }
+            Instruction::ListCsv | Instruction::ListJson => {
+                let value = self.value_stack.pop().unwrap();
+                let res = match ins {
+                    Instruction::ListCsv => ops::to_csv(mc, &value)?,
+                    _ => {
+                        let json = value.to_simple()?.into_json(self.settings.json_objects)?;
+                        json.to_string()
+                    }
+                };
+                ops::check_string_size(&self.settings, res.len())?;
+                ops::track_heap(&self.settings, &mut global_context.heap_usage, res.len())?;
+                self.value_stack.push(Gc::allocate(mc, res).into());
+                self.pos = aft_pos;
+            }
+            Instruction::ListRank => {
+                let list = self.value_stack.pop().unwrap().as_list()?;
+                self.value_stack.push((ops::dims(&list.into()).len() as f64).into());
+                self.pos = aft_pos;
+            }
+            Instruction::ListReshape => {
+                let dims = self.value_stack.pop().unwrap();
+                let list = self.value_stack.pop().unwrap().as_list()?;
+                let dims = match &dims {
+                    Value::List(x) => x.read().iter().map(|x| x.to_number()).collect::<Result<Vec<_>, _>>()?,
+                    x => vec![x.to_number()?],
+                };
+                let dims: Vec<usize> = dims.into_iter().map(|x| libm::fmax(libm::floor(x), 0.0) as usize).collect();
+
+                let total = ops::reshape_size(&self.settings, &dims)?;
+                ops::track_heap(&self.settings, &mut global_context.heap_usage, total.saturating_mul(mem::size_of::<Value>()))?;
+
+                let res = ops::reshape(mc, &ops::flatten(&self.settings, &list.into())?, &dims);
+                self.value_stack.push(res);
+                self.pos = aft_pos;
+            }
+
             Instruction::Strcat { args } => {
                 let mut values = Vec::with_capacity(args);
                 for _ in 0..args {
@@ -423,7 +800,9 @@ impl<'gc, S: System> Process<'gc, S> {
                 let mut res = String::new();
                 for value in values.iter().rev() {
                     res += value.to_string(mc)?.as_str();
+                    ops::check_string_size(&self.settings, res.len())?;
                 }
+                ops::track_heap(&self.settings, &mut global_context.heap_usage, res.len())?;
                 self.value_stack.push(Gc::allocate(mc, res).into());
                 self.pos = aft_pos;
             }
@@ -431,7 +810,11 @@ impl<'gc, S: System> Process<'gc, S> {
             Instruction::BinaryOp { op } => {
                 let b = self.value_stack.pop().unwrap();
                 let a = self.value_stack.pop().unwrap();
-                self.value_stack.push(ops::binary_op(mc, &a, &b, op)?);
+                let res = ops::binary_op(mc, &a, &b, op)?;
+                if let BinaryOp::SplitCustom = op {
+                    ops::track_value(&self.settings, &mut global_context.heap_usage, &res)?;
+                }
+                self.value_stack.push(res);
                 self.pos = aft_pos;
             }
             Instruction::Eq => {
@@ -440,9 +823,47 @@ impl<'gc, S: System> Process<'gc, S> {
                 self.value_stack.push(ops::check_eq(&a, &b).into());
                 self.pos = aft_pos;
             }
+            Instruction::Neq => {
+                let b = self.value_stack.pop().unwrap();
+                let a = self.value_stack.pop().unwrap();
+                self.value_stack.push((!ops::check_eq(&a, &b)).into());
+                self.pos = aft_pos;
+            }
+            Instruction::Identical => {
+                let b = self.value_stack.pop().unwrap();
+                let a = self.value_stack.pop().unwrap();
+                self.value_stack.push(ops::check_identical(&a, &b).into());
+                self.pos = aft_pos;
+            }
             Instruction::UnaryOp { op } => {
                 let x = self.value_stack.pop().unwrap();
-                self.value_stack.push(ops::unary_op(mc, &x, op)?);
+                let res = ops::unary_op(mc, &x, op)?;
+                if let UnaryOp::SplitLetter | UnaryOp::SplitWord | UnaryOp::SplitTab | UnaryOp::SplitCR | UnaryOp::SplitLF | UnaryOp::SplitCsv | UnaryOp::SplitJson | UnaryOp::StrLower | UnaryOp::StrUpper = op {
+                    ops::track_value(&self.settings, &mut global_context.heap_usage, &res)?;
+                }
+                self.value_stack.push(res);
+                self.pos = aft_pos;
+            }
+            Instruction::Substring => {
+                let end = self.value_stack.pop().unwrap();
+                let start = self.value_stack.pop().unwrap();
+                let text = self.value_stack.pop().unwrap();
+                let res = ops::substring(mc, &text, start.to_number()?, end.to_number()?)?;
+                ops::track_value(&self.settings, &mut global_context.heap_usage, &res)?;
+                self.value_stack.push(res);
+                self.pos = aft_pos;
+            }
+
+            Instruction::Random => {
+                let b = self.value_stack.pop().unwrap();
+                let a = self.value_stack.pop().unwrap();
+                let res = ops::random(mc, &a, &b, system)?;
+                self.value_stack.push(res);
+                self.pos = aft_pos;
+            }
+            Instruction::TypeQuery { ty } => {
+                let x = self.value_stack.pop().unwrap();
+                self.value_stack.push(ops::type_query(&x, ty).into());
                 self.pos = aft_pos;
             }
 
@@ -488,7 +909,7 @@ impl<'gc, S: System> Process<'gc, S> {
                 self.call_stack.push((ReturnPoint { pos: aft_pos, warp_counter: self.warp_counter, value_stack_size: self.value_stack.len() }, locals));
                 self.pos = pos;
             }
-            Instruction::MakeClosure { pos, params, captures } => {
+            Instruction::MakeClosure { pos, params, captures, empty_slots } => {
                 debug_assert_eq!(self.meta_stack.len(), params + captures);
                 let captures: Vec<_> = self.meta_stack.drain(params..).collect();
                 let params: Vec<_> = self.meta_stack.drain(..).collect();
@@ -497,37 +918,84 @@ impl<'gc, S: System> Process<'gc, S> {
                 for var in captures.iter() {
                     caps.redefine_or_define(var, lookup_var!(mut var).alias(mc));
                 }
-                self.value_stack.push(GcCell::allocate(mc, Closure { pos, params, captures: caps }).into());
+                self.value_stack.push(GcCell::allocate(mc, Closure { pos, params, captures: caps, empty_slots }).into());
                 self.pos = aft_pos;
             }
-            Instruction::CallClosure { args } => {
+            Instruction::CallClosure { .. } | Instruction::CallClosureDyn => {
                 let closure = self.value_stack.pop().unwrap().as_closure()?;
-                let mut closure = closure.write(mc);
-                if closure.params.len() != args {
-                    return Err(ErrorCause::ClosureArgCount { expected: closure.params.len(), got: args });
-                }
+                let args = match ins {
+                    Instruction::CallClosure { args } => self.value_stack.drain(self.value_stack.len() - args..).collect(),
+                    _ => self.value_stack.pop().unwrap().as_list()?.read().clone(),
+                };
 
-                let mut locals = SymbolTable::default();
-                for (k, v) in closure.captures.iter_mut() {
-                    locals.redefine_or_define(k, v.alias(mc));
-                }
-                for var in closure.params.iter().rev() {
-                    locals.redefine_or_define(var, self.value_stack.pop().unwrap().into());
-                }
+                let mut closure = closure.write(mc);
+                let locals = prep_closure_call(mc, &mut closure, args)?;
                 self.call_stack.push((ReturnPoint { pos: aft_pos, warp_counter: self.warp_counter, value_stack_size: self.value_stack.len() }, locals));
                 self.pos = closure.pos;
             }
+            Instruction::ListHof { op } => {
+                let resuming = matches!(self.hof_stack.last(), Some(x) if x.pos == self.pos && x.call_depth == self.call_stack.len());
+                let args = match resuming {
+                    false => {
+                        let closure = self.value_stack.pop().unwrap().as_closure()?;
+                        let list = self.value_stack.pop().unwrap();
+                        let state = HofState::new(self.pos, self.call_stack.len(), op, closure, list)?;
+                        self.hof_stack.push(state);
+                        self.hof_stack.last_mut().unwrap().advance(None)?
+                    }
+                    true => {
+                        let res = self.value_stack.pop().unwrap();
+                        self.hof_stack.last_mut().unwrap().advance(Some(res))?
+                    }
+                };
+
+                match args {
+                    None => {
+                        let res = self.hof_stack.pop().unwrap().finish(mc);
+                        if let HofOp::Map | HofOp::Keep | HofOp::Sort = op {
+                            ops::track_value(&self.settings, &mut global_context.heap_usage, &res)?;
+                        }
+                        self.value_stack.push(res);
+                        self.pos = aft_pos;
+                    }
+                    Some(args) => {
+                        if self.call_stack.len() >= self.settings.max_call_depth {
+                            return Err(ErrorCause::CallDepthLimit { limit: self.settings.max_call_depth });
+                        }
+
+                        let mut closure = self.hof_stack.last().unwrap().closure.write(mc);
+                        let locals = prep_closure_call(mc, &mut closure, args)?;
+                        self.call_stack.push((ReturnPoint { pos: self.pos, warp_counter: self.warp_counter, value_stack_size: self.value_stack.len() }, locals));
+                        self.pos = closure.pos;
+
+                        if resuming && self.warp_counter == 0 { return Ok(ProcessStep::Yield) }
+                    }
+                }
+            }
             Instruction::CallRpc { service, rpc, args } => {
                 debug_assert_eq!(self.meta_stack.len(), args);
                 let mut args_vec = Vec::with_capacity(args);
                 for _ in 0..args {
                     let arg_name = self.meta_stack.pop().unwrap();
-                    let value = self.value_stack.pop().unwrap().to_simple()?.try_into()?;
+                    let value = self.value_stack.pop().unwrap().to_simple()?.into_json(self.settings.json_objects)?;
                     args_vec.push((arg_name, value));
                 }
                 args_vec.reverse();
-                let key = system.call_rpc(service.to_owned(), rpc.to_owned(), args_vec)?;
-                self.defer = Some(Defer::RpcResult { key, aft_pos });
+                self.defer = Some(Defer::call_rpc(system, &self.settings, &self.orphaned_rpcs, service.to_owned(), rpc.to_owned(), args_vec, aft_pos)?);
+            }
+            Instruction::CallRpcDyn => {
+                let args = self.value_stack.pop().unwrap().as_list()?;
+                let rpc = self.value_stack.pop().unwrap().to_string(mc)?;
+                let service = self.value_stack.pop().unwrap().to_string(mc)?;
+
+                let mut args_vec = Vec::with_capacity(args.read().len());
+                for (i, arg) in args.read().iter().enumerate() {
+                    let pair = arg.as_list()?;
+                    let pair = pair.read();
+                    if pair.len() != 2 { return Err(ErrorCause::RpcArgNotPair { index: i + 1 }) }
+                    args_vec.push((pair[0].to_string(mc)?.as_str().to_owned(), pair[1].to_simple()?.into_json(self.settings.json_objects)?));
+                }
+                self.defer = Some(Defer::call_rpc(system, &self.settings, &self.orphaned_rpcs, service.as_str().to_owned(), rpc.as_str().to_owned(), args_vec, aft_pos)?);
             }
             Instruction::Return => {
                 let (return_point, _) = self.call_stack.pop().unwrap();
@@ -568,13 +1036,48 @@ impl<'gc, S: System> Process<'gc, S> {
                 self.settings.printer.as_ref()(if is_empty { None } else { Some(value) }, &*entity);
                 self.pos = aft_pos;
             }
+            Instruction::Sleep => {
+                let secs = self.value_stack.pop().unwrap().to_number()?;
+                let wake_time = system.time_ms()?.saturating_add((secs * 1000.0).max(0.0) as u64);
+                self.defer = Some(Defer::Sleep { wake_time, aft_pos });
+                return Ok(ProcessStep::Yield);
+            }
         }
 
         Ok(ProcessStep::Normal)
     }
 }
 
+
+/// Creates the symbol table for a call to `closure` with the given arguments, which includes the closure's captures.
+/// This handles closures with empty slots as described in [`Instruction::MakeClosure`].
+fn prep_closure_call<'gc>(mc: MutationContext<'gc, '_>, closure: &mut Closure<'gc>, args: Vec<Value<'gc>>) -> Result<SymbolTable<'gc>, ErrorCause> {
+    let mut locals = SymbolTable::default();
+    for (k, v) in closure.captures.iter_mut() {
+        locals.redefine_or_define(k, v.alias(mc));
+    }
+
+    if closure.params.is_empty() && closure.empty_slots > 0 {
+        let inputs = match args.len() {
+            0 => vec![Gc::allocate(mc, String::new()).into(); closure.empty_slots],
+            1 => vec![args[0]; closure.empty_slots],
+            x if x == closure.empty_slots => args,
+            x => return Err(ErrorCause::ClosureArgCount { expected: closure.empty_slots, got: x }),
+        };
+        locals.redefine_or_define(EMPTY_SLOTS_VAR, Value::from(GcCell::allocate(mc, inputs)).into());
+    } else {
+        if closure.params.len() != args.len() {
+            return Err(ErrorCause::ClosureArgCount { expected: closure.params.len(), got: args.len() });
+        }
+        for (var, value) in iter::zip(&closure.params, args) {
+            locals.redefine_or_define(var, value.into());
+        }
+    }
+    Ok(locals)
+}
 mod ops {
+    use std::cmp::Ordering;
+
     use super::*;
 
     fn as_list<'gc>(v: &Value<'gc>) -> Option<GcCell<'gc, Vec<Value<'gc>>>> {
@@ -600,6 +1103,54 @@ mod ops {
         Ok(index as usize - 1)
     }
 
+    pub(super) fn check_list_size(settings: &Settings, size: usize) -> Result<(), ErrorCause> {
+        match settings.max_list_size {
+            Some(limit) if size > limit => Err(ErrorCause::ListSizeLimit { size, limit }),
+            _ => Ok(()),
+        }
+    }
+    pub(super) fn check_string_size(settings: &Settings, size: usize) -> Result<(), ErrorCause> {
+        match settings.max_string_size {
+            Some(limit) if size > limit => Err(ErrorCause::StringSizeLimit { size, limit }),
+            _ => Ok(()),
+        }
+    }
+    pub(super) fn track_heap(settings: &Settings, heap_usage: &mut usize, bytes: usize) -> Result<(), ErrorCause> {
+        let size = heap_usage.saturating_add(bytes);
+        match settings.max_heap_size {
+            Some(limit) if size > limit => Err(ErrorCause::HeapSizeLimit { size, limit }),
+            _ => {
+                *heap_usage = size;
+                Ok(())
+            }
+        }
+    }
+    /// Checks all the lists and strings in a newly-created `value` against the size limits
+    /// and adds its (approximate) total size to the heap usage.
+    pub(super) fn track_value<'gc>(settings: &Settings, heap_usage: &mut usize, value: &Value<'gc>) -> Result<(), ErrorCause> {
+        fn size_of<'gc>(settings: &Settings, value: &Value<'gc>, cache: &mut BTreeSet<Identity<'gc>>) -> Result<usize, ErrorCause> {
+            Ok(match value {
+                Value::String(x) => {
+                    check_string_size(settings, x.len())?;
+                    x.len()
+                }
+                Value::List(x) => {
+                    if !cache.insert(value.identity()) { return Ok(0) }
+                    let x = x.read();
+                    check_list_size(settings, x.len())?;
+                    let mut total = x.len() * mem::size_of::<Value>();
+                    for item in x.iter() {
+                        total = total.saturating_add(size_of(settings, item, cache)?);
+                    }
+                    total
+                }
+                _ => 0,
+            })
+        }
+        let bytes = size_of(settings, value, &mut Default::default())?;
+        track_heap(settings, heap_usage, bytes)
+    }
+
     pub(super) fn json_to_value<'gc>(mc: MutationContext<'gc, '_>, json: Json, src: Option<Cow<str>>) -> Result<Value<'gc>, ErrorCause> {
         let src = src.unwrap_or_else(|| Cow::Owned(json.to_string())); // we need this in case parsing fails to give a good error message
         match SimpleValue::try_from(json) {
@@ -611,7 +1162,7 @@ mod ops {
 
     const DEG_TO_RAD: f64 = std::f64::consts::PI / 180.0;
 
-    fn binary_op_impl<'gc>(mc: MutationContext<'gc, '_>, a: &Value<'gc>, b: &Value<'gc>, matrix_mode: bool, cache: &mut BTreeMap<(Identity<'gc>, Identity<'gc>, bool), Value<'gc>>, scalar_op: fn(MutationContext<'gc, '_>, &Value<'gc>, &Value<'gc>) -> Result<Value<'gc>, ErrorCause>) -> Result<Value<'gc>, ErrorCause> {
+    fn binary_op_impl<'gc>(mc: MutationContext<'gc, '_>, a: &Value<'gc>, b: &Value<'gc>, matrix_mode: bool, cache: &mut BTreeMap<(Identity<'gc>, Identity<'gc>, bool), Value<'gc>>, scalar_op: &dyn Fn(MutationContext<'gc, '_>, &Value<'gc>, &Value<'gc>) -> Result<Value<'gc>, ErrorCause>) -> Result<Value<'gc>, ErrorCause> {
         let cache_key = (a.identity(), b.identity(), matrix_mode);
         Ok(match cache.get(&cache_key) {
             Some(x) => *x,
@@ -656,26 +1207,56 @@ mod ops {
             }
         })
     }
+    /// Compares two scalar values Snap-style: numerically if both are numbers (or numeric strings), and otherwise as case-insensitive text.
+    /// Returns [`None`] if the values are unordered, which is only the case for `NaN`.
+    fn compare<'gc>(mc: MutationContext<'gc, '_>, a: &Value<'gc>, b: &Value<'gc>) -> Result<Option<Ordering>, ErrorCause> {
+        Ok(match (a.to_number(), b.to_number()) {
+            (Ok(a), Ok(b)) => a.partial_cmp(&b),
+            _ => Some(a.to_string(mc)?.to_lowercase().cmp(&b.to_string(mc)?.to_lowercase())),
+        })
+    }
     pub(super) fn binary_op<'gc, 'a>(mc: MutationContext<'gc, '_>, a: &'a Value<'gc>, b: &'a Value<'gc>, op: BinaryOp) -> Result<Value<'gc>, ErrorCause> {
         let mut cache = Default::default();
         match op {
-            BinaryOp::Add     => binary_op_impl(mc, a, b, true, &mut cache, |_, a, b| Ok((a.to_number()? + b.to_number()?).into())),
-            BinaryOp::Sub     => binary_op_impl(mc, a, b, true, &mut cache, |_, a, b| Ok((a.to_number()? - b.to_number()?).into())),
-            BinaryOp::Mul     => binary_op_impl(mc, a, b, true, &mut cache, |_, a, b| Ok((a.to_number()? * b.to_number()?).into())),
-            BinaryOp::Div     => binary_op_impl(mc, a, b, true, &mut cache, |_, a, b| Ok((a.to_number()? / b.to_number()?).into())),
-            BinaryOp::Pow     => binary_op_impl(mc, a, b, true, &mut cache, |_, a, b| Ok(libm::pow(a.to_number()?, b.to_number()?).into())),
-            BinaryOp::Log     => binary_op_impl(mc, a, b, true, &mut cache, |_, a, b| Ok((libm::log2(b.to_number()?) / libm::log2(a.to_number()?)).into())),
-            BinaryOp::Greater => binary_op_impl(mc, a, b, true, &mut cache, |_, a, b| Ok((a.to_number()? > b.to_number()?).into())),
-            BinaryOp::Less    => binary_op_impl(mc, a, b, true, &mut cache, |_, a, b| Ok((a.to_number()? < b.to_number()?).into())),
-            BinaryOp::Mod     => binary_op_impl(mc, a, b, true, &mut cache, |_, a, b| {
+            BinaryOp::Add     => binary_op_impl(mc, a, b, true, &mut cache, &|_, a, b| Ok((a.to_number()? + b.to_number()?).into())),
+            BinaryOp::Sub     => binary_op_impl(mc, a, b, true, &mut cache, &|_, a, b| Ok((a.to_number()? - b.to_number()?).into())),
+            BinaryOp::Mul     => binary_op_impl(mc, a, b, true, &mut cache, &|_, a, b| Ok((a.to_number()? * b.to_number()?).into())),
+            BinaryOp::Div     => binary_op_impl(mc, a, b, true, &mut cache, &|_, a, b| Ok((a.to_number()? / b.to_number()?).into())),
+            BinaryOp::Pow     => binary_op_impl(mc, a, b, true, &mut cache, &|_, a, b| Ok(libm::pow(a.to_number()?, b.to_number()?).into())),
+            BinaryOp::Log     => binary_op_impl(mc, a, b, true, &mut cache, &|_, a, b| Ok((libm::log2(b.to_number()?) / libm::log2(a.to_number()?)).into())),
+            BinaryOp::Greater   => binary_op_impl(mc, a, b, true, &mut cache, &|mc, a, b| Ok(matches!(compare(mc, a, b)?, Some(Ordering::Greater)).into())),
+            BinaryOp::GreaterEq => binary_op_impl(mc, a, b, true, &mut cache, &|mc, a, b| Ok(matches!(compare(mc, a, b)?, Some(Ordering::Greater | Ordering::Equal)).into())),
+            BinaryOp::Less      => binary_op_impl(mc, a, b, true, &mut cache, &|mc, a, b| Ok(matches!(compare(mc, a, b)?, Some(Ordering::Less)).into())),
+            BinaryOp::LessEq    => binary_op_impl(mc, a, b, true, &mut cache, &|mc, a, b| Ok(matches!(compare(mc, a, b)?, Some(Ordering::Less | Ordering::Equal)).into())),
+            BinaryOp::Atan2   => binary_op_impl(mc, a, b, true, &mut cache, &|_, a, b| Ok((libm::atan2(a.to_number()?, b.to_number()?) / DEG_TO_RAD).into())),
+            BinaryOp::Min     => binary_op_impl(mc, a, b, true, &mut cache, &|_, a, b| Ok(a.to_number()?.min(b.to_number()?).into())),
+            BinaryOp::Max     => binary_op_impl(mc, a, b, true, &mut cache, &|_, a, b| Ok(a.to_number()?.max(b.to_number()?).into())),
+            BinaryOp::Mod     => binary_op_impl(mc, a, b, true, &mut cache, &|_, a, b| {
                 let (a, b) = (a.to_number()?, b.to_number()?);
                 Ok(if a.is_sign_positive() == b.is_sign_positive() { a % b } else { b + (a % -b) }.into())
             }),
 
-            BinaryOp::SplitCustom => binary_op_impl(mc, a, b, true, &mut cache, |mc, a, b| {
+            BinaryOp::SplitCustom => binary_op_impl(mc, a, b, true, &mut cache, &|mc, a, b| {
                 let (text, pattern) = (a.to_string(mc)?, b.to_string(mc)?);
                 Ok(GcCell::allocate(mc, text.split(pattern.as_str()).map(|x| Gc::allocate(mc, x.to_owned()).into()).collect::<Vec<_>>()).into())
             }),
+
+            BinaryOp::StrGet => binary_op_impl(mc, a, b, true, &mut cache, &|mc, a, b| {
+                let (index, text) = (a.to_number()?, b.to_string(mc)?);
+                let ch = match index.fract() == 0.0 && index >= 1.0 {
+                    true => text.chars().nth(index as usize - 1),
+                    false => None,
+                };
+                Ok(Gc::allocate(mc, ch.map(String::from).unwrap_or_default()).into())
+            }),
+            BinaryOp::StrContains => binary_op_impl(mc, a, b, true, &mut cache, &|mc, a, b| Ok(a.to_string(mc)?.contains(b.to_string(mc)?.as_str()).into())),
+            BinaryOp::StrIndexOf => binary_op_impl(mc, a, b, true, &mut cache, &|mc, a, b| {
+                let (pattern, text) = (a.to_string(mc)?, b.to_string(mc)?);
+                Ok(match text.find(pattern.as_str()) {
+                    Some(pos) => (text[..pos].chars().count() + 1) as f64,
+                    None => 0.0,
+                }.into())
+            }),
         }
     }
 
@@ -717,7 +1298,13 @@ mod ops {
             UnaryOp::Asin     => unary_op_impl(mc, x, &mut cache, &|_, x| Ok((libm::asin(x.to_number()?) / DEG_TO_RAD).into())),
             UnaryOp::Acos     => unary_op_impl(mc, x, &mut cache, &|_, x| Ok((libm::acos(x.to_number()?) / DEG_TO_RAD).into())),
             UnaryOp::Atan     => unary_op_impl(mc, x, &mut cache, &|_, x| Ok((libm::atan(x.to_number()?) / DEG_TO_RAD).into())),
+            UnaryOp::Sign     => unary_op_impl(mc, x, &mut cache, &|_, x| {
+                let x = x.to_number()?;
+                Ok(if x > 0.0 { 1.0 } else if x < 0.0 { -1.0 } else { x }.into())
+            }),
             UnaryOp::Strlen   => unary_op_impl(mc, x, &mut cache, &|_, x| Ok((x.to_string(mc)?.chars().count() as f64).into())),
+            UnaryOp::StrLower => unary_op_impl(mc, x, &mut cache, &|mc, x| Ok(Gc::allocate(mc, x.to_string(mc)?.to_lowercase()).into())),
+            UnaryOp::StrUpper => unary_op_impl(mc, x, &mut cache, &|mc, x| Ok(Gc::allocate(mc, x.to_string(mc)?.to_uppercase()).into())),
 
             UnaryOp::SplitLetter => unary_op_impl(mc, x, &mut cache, &|mc, x| {
                 Ok(GcCell::allocate(mc, x.to_string(mc)?.chars().map(|x| Gc::allocate(mc, x.to_string()).into()).collect::<Vec<_>>()).into())
@@ -735,9 +1322,10 @@ mod ops {
                 Ok(GcCell::allocate(mc, x.to_string(mc)?.lines().map(|x| Gc::allocate(mc, x.to_owned()).into()).collect::<Vec<_>>()).into())
             }),
             UnaryOp::SplitCsv => unary_op_impl(mc, x, &mut cache, &|mc, x| {
-                let lines = x.to_string(mc)?.lines().map(|line| GcCell::allocate(mc, line.split(',').map(|x| Gc::allocate(mc, x.to_owned()).into()).collect::<Vec<_>>()).into()).collect::<Vec<_>>();
+                let (lines, terminated) = parse_csv(&x.to_string(mc)?);
+                let lines = lines.into_iter().map(|line| GcCell::allocate(mc, line.into_iter().map(|x| Gc::allocate(mc, x).into()).collect::<Vec<_>>()).into()).collect::<Vec<_>>();
                 Ok(match lines.len() {
-                    1 => lines.into_iter().next().unwrap(),
+                    1 if !terminated => lines.into_iter().next().unwrap(),
                     _ => GcCell::allocate(mc, lines).into(),
                 })
             }),
@@ -769,6 +1357,246 @@ mod ops {
             }),
         }
     }
+    /// Gets a random number between `a` and `b` (inclusive, in either order), vectorized over both arguments.
+    /// The result is an integer if both bounds are integers, and a real number otherwise.
+    pub(super) fn random<'gc, S: System>(mc: MutationContext<'gc, '_>, a: &Value<'gc>, b: &Value<'gc>, system: &S) -> Result<Value<'gc>, ErrorCause> {
+        binary_op_impl(mc, a, b, true, &mut Default::default(), &|_, a, b| {
+            let (a, b) = (a.to_number()?, b.to_number()?);
+            let (low, high) = (a.min(b), a.max(b));
+            Ok(match low.fract() == 0.0 && high.fract() == 0.0 {
+                true => libm::floor(system.random()? * (high - low + 1.0)) + low,
+                false => system.random()? * (high - low) + low,
+            }.into())
+        })
+    }
+    pub(super) fn type_query(x: &Value, ty: BasicType) -> bool {
+        match ty {
+            BasicType::Number => x.to_number().is_ok(),
+            BasicType::Text => matches!(x, Value::String(_)) && x.to_number().is_err(),
+            BasicType::Bool => matches!(x, Value::Bool(_)),
+            BasicType::List => matches!(x, Value::List(_)),
+        }
+    }
+    /// Parses CSV text (as in RFC 4180) into a list of records, each of which is a list of fields.
+    /// Records may end in either CRLF or LF, and a trailing line break does not start a new record.
+    /// Also returns whether the last record ended in a line break, which [`to_csv`] uses to mark tables.
+    pub(super) fn parse_csv(text: &str) -> (Vec<Vec<String>>, bool) {
+        let mut records = vec![];
+        let mut record = vec![];
+        let mut field = String::new();
+        let mut in_record = false;
+        let mut in_quotes = false;
+
+        let mut chars = text.chars().peekable();
+        while let Some(ch) = chars.next() {
+            if in_quotes {
+                match ch {
+                    '"' if chars.peek() == Some(&'"') => { chars.next(); field.push('"'); }
+                    '"' => in_quotes = false,
+                    _ => field.push(ch),
+                }
+                continue;
+            }
+            match ch {
+                '\r' if chars.peek() == Some(&'\n') => continue,
+                '\r' | '\n' => {
+                    record.push(mem::take(&mut field));
+                    records.push(mem::take(&mut record));
+                    in_record = false;
+                    continue;
+                }
+                ',' => record.push(mem::take(&mut field)),
+                '"' if field.is_empty() => in_quotes = true,
+                _ => field.push(ch),
+            }
+            in_record = true;
+        }
+        let terminated = !in_record && !records.is_empty();
+        if in_record {
+            record.push(field);
+            records.push(record);
+        }
+        (records, terminated)
+    }
+    /// Encodes a value as CSV text (see [`Instruction::ListCsv`]).
+    pub(super) fn to_csv<'gc>(mc: MutationContext<'gc, '_>, value: &Value<'gc>) -> Result<String, ErrorCause> {
+        fn encode_field<'gc>(mc: MutationContext<'gc, '_>, value: &Value<'gc>, lone: bool, res: &mut String) -> Result<(), ErrorCause> {
+            let text = match value {
+                Value::Bool(x) => x.to_string(),
+                x => (*x.to_string(mc)?).clone(),
+            };
+            // a lone empty field is quoted so that it is not read back as an empty record
+            if text.contains([',', '"', '\n', '\r']) || (lone && text.is_empty()) {
+                res.push('"');
+                res.push_str(&text.replace('"', "\"\""));
+                res.push('"');
+            } else {
+                res.push_str(&text);
+            }
+            Ok(())
+        }
+        fn encode_record<'gc>(mc: MutationContext<'gc, '_>, items: &[Value<'gc>], res: &mut String) -> Result<(), ErrorCause> {
+            for (i, item) in items.iter().enumerate() {
+                if i != 0 { res.push(',') }
+                encode_field(mc, item, items.len() == 1, res)?;
+            }
+            Ok(())
+        }
+
+        let items = match value {
+            Value::List(x) => x.read().clone(),
+            x => vec![*x],
+        };
+        let mut res = String::new();
+        if items.iter().any(|x| matches!(x, Value::List(_))) {
+            for item in items.iter() {
+                match item {
+                    Value::List(x) => encode_record(mc, &x.read(), &mut res)?,
+                    x => encode_field(mc, x, true, &mut res)?,
+                }
+                res.push('\n');
+            }
+        } else {
+            encode_record(mc, &items, &mut res)?;
+        }
+        Ok(res)
+    }
+    pub(super) fn sort<'gc>(mc: MutationContext<'gc, '_>, items: &mut [Value<'gc>]) -> Result<(), ErrorCause> {
+        // equivalent to sorting with compare, but computes the keys up front since conversions can fail
+        let mut keys = Vec::with_capacity(items.len());
+        for item in items.iter() {
+            keys.push((item.to_number().ok(), item.to_string(mc)?.to_lowercase(), *item));
+        }
+        // numbers come before text so that mixed lists still have a total order
+        keys.sort_by(|a, b| match (a.0, b.0) {
+            (Some(x), Some(y)) => x.total_cmp(&y),
+            (Some(_), None) => Ordering::Less,
+            (None, Some(_)) => Ordering::Greater,
+            (None, None) => a.1.cmp(&b.1),
+        });
+        for (dest, (_, _, item)) in iter::zip(items, keys) {
+            *dest = item;
+        }
+        Ok(())
+    }
+    // flatten and dims walk the list iteratively so that deeply-nested lists cannot overflow the native stack
+    type ListFrame<'gc> = (GcCell<'gc, Vec<Value<'gc>>>, usize);
+    pub(super) fn flatten<'gc>(settings: &Settings, value: &Value<'gc>) -> Result<Vec<Value<'gc>>, ErrorCause> {
+        let mut res = vec![];
+        let mut visiting = BTreeSet::new();
+        let mut stack: Vec<ListFrame> = vec![];
+        match value {
+            Value::List(x) => {
+                visiting.insert(value.identity());
+                stack.push((*x, 0));
+            }
+            x => res.push(*x),
+        }
+        while let Some((list, pos)) = stack.last_mut() {
+            let list = *list;
+            let item = list.read().get(*pos).copied();
+            *pos += 1;
+            match item {
+                None => {
+                    visiting.remove(&Value::List(list).identity());
+                    stack.pop();
+                }
+                Some(item @ Value::List(x)) if visiting.insert(item.identity()) => stack.push((x, 0)),
+                Some(item) => {
+                    res.push(item);
+                    check_list_size(settings, res.len())?;
+                }
+            }
+        }
+        Ok(res)
+    }
+    pub(super) fn dims<'gc>(value: &Value<'gc>) -> Vec<usize> {
+        fn enter<'gc>(list: GcCell<'gc, Vec<Value<'gc>>>, stack: &mut Vec<ListFrame<'gc>>, res: &mut Vec<usize>) {
+            let depth = stack.len();
+            if res.len() <= depth { res.push(0) }
+            res[depth] = res[depth].max(list.read().len());
+            stack.push((list, 0));
+        }
+        let mut res = vec![];
+        let mut visiting = BTreeSet::new();
+        let mut stack = vec![];
+        if let Value::List(x) = value {
+            visiting.insert(value.identity());
+            enter(*x, &mut stack, &mut res);
+        }
+        while let Some((list, pos)) = stack.last_mut() {
+            let list = *list;
+            let item = list.read().get(*pos).copied();
+            *pos += 1;
+            match item {
+                None => {
+                    visiting.remove(&Value::List(list).identity());
+                    stack.pop();
+                }
+                Some(item @ Value::List(x)) if visiting.insert(item.identity()) => enter(x, &mut stack, &mut res),
+                Some(_) => (),
+            }
+        }
+        res
+    }
+    pub(super) fn columns<'gc>(mc: MutationContext<'gc, '_>, list: GcCell<'gc, Vec<Value<'gc>>>) -> Value<'gc> {
+        let rows = list.read();
+        let width = rows.iter().map(|x| match x { Value::List(x) => x.read().len(), _ => 1 }).max().unwrap_or(0);
+        let empty: Value = Gc::allocate(mc, String::new()).into();
+        let res = (0..width).map(|i| {
+            let column = rows.iter().map(|x| match x {
+                Value::List(x) => x.read().get(i).copied().unwrap_or(empty),
+                x => *x,
+            }).collect::<Vec<_>>();
+            GcCell::allocate(mc, column).into()
+        }).collect::<Vec<_>>();
+        GcCell::allocate(mc, res).into()
+    }
+    pub(super) fn distribution<'gc>(mc: MutationContext<'gc, '_>, list: GcCell<'gc, Vec<Value<'gc>>>) -> Value<'gc> {
+        let mut counts: Vec<(Value<'gc>, usize)> = vec![];
+        for item in list.read().iter() {
+            match counts.iter_mut().find(|x| check_eq(&x.0, item)) {
+                Some(x) => x.1 += 1,
+                None => counts.push((*item, 1)),
+            }
+        }
+        GcCell::allocate(mc, counts.into_iter().map(|(value, count)| GcCell::allocate(mc, vec![value, (count as f64).into()]).into()).collect::<Vec<_>>()).into()
+    }
+    /// Returns the total number of list slots needed to reshape a list to `dims`, or fails if any level of the result is too large.
+    pub(super) fn reshape_size(settings: &Settings, dims: &[usize]) -> Result<usize, ErrorCause> {
+        let too_big = || ErrorCause::ListSizeLimit { size: usize::MAX, limit: settings.max_list_size.unwrap_or(usize::MAX) };
+        let (mut level, mut total) = (1usize, 0usize);
+        for &dim in dims {
+            check_list_size(settings, dim)?;
+            level = level.checked_mul(dim).ok_or_else(too_big)?;
+            check_list_size(settings, level)?;
+            total = total.checked_add(level).ok_or_else(too_big)?;
+        }
+        Ok(total)
+    }
+    pub(super) fn reshape<'gc>(mc: MutationContext<'gc, '_>, items: &[Value<'gc>], dims: &[usize]) -> Value<'gc> {
+        if dims.is_empty() { return GcCell::allocate(mc, items.to_vec()).into() }
+
+        // build the result from the innermost lists outward so that many dimensions cannot overflow the native stack
+        let counts: Vec<usize> = dims.iter().scan(1, |count, &dim| { *count *= dim; Some(*count) }).collect();
+        let empty: Value = Gc::allocate(mc, String::new()).into();
+        let mut level: Vec<Value> = (0..counts[dims.len() - 1]).map(|i| if items.is_empty() { empty } else { items[i % items.len()] }).collect();
+        for (i, &dim) in dims.iter().enumerate().rev() {
+            let lists = if i == 0 { 1 } else { counts[i - 1] };
+            let mut values = level.into_iter();
+            level = (0..lists).map(|_| GcCell::allocate(mc, values.by_ref().take(dim).collect::<Vec<_>>()).into()).collect();
+        }
+        debug_assert_eq!(level.len(), 1);
+        level[0]
+    }
+    pub(super) fn substring<'gc>(mc: MutationContext<'gc, '_>, text: &Value<'gc>, start: f64, end: f64) -> Result<Value<'gc>, ErrorCause> {
+        unary_op_impl(mc, text, &mut Default::default(), &|mc, x| {
+            let text = x.to_string(mc)?;
+            let start = libm::fmax(libm::floor(start) - 1.0, 0.0) as usize;
+            let end = libm::fmax(libm::floor(end), 0.0) as usize;
+            Ok(Gc::allocate(mc, text.chars().skip(start).take(end.saturating_sub(start)).collect::<String>()).into())
+        })
+    }
     pub(super) fn index_list<'gc>(mc: MutationContext<'gc, '_>, list: &Value<'gc>, index: &Value<'gc>) -> Result<Value<'gc>, ErrorCause> {
         let list = list.as_list()?;
         let list = list.read();
@@ -787,7 +1615,7 @@ mod ops {
             (Value::String(a), Value::String(b)) => a.to_lowercase() == b.to_lowercase(),
             (Value::Number(n), Value::String(s)) | (Value::String(s), Value::Number(n)) => match s.parse::<f64>() {
                 Ok(s) => s == *n,
-                Err(_) => **s == n.to_string(),
+                Err(_) => **s == number_to_string(*n),
             }
 
             (Value::Closure(a), Value::Closure(b)) => a.as_ptr() == b.as_ptr(),
@@ -805,9 +1633,18 @@ mod ops {
 
             (Value::Entity(a), Value::Entity(b)) => a.as_ptr() == b.as_ptr(),
             (Value::Entity(_), _) | (_, Value::Entity(_)) => false,
+
+            (Value::Blob(a), Value::Blob(b)) => **a == **b,
+            (Value::Blob(_), _) | (_, Value::Blob(_)) => false,
         }
     }
     pub(super) fn check_eq<'gc, 'a>(a: &'a Value<'gc>, b: &'a Value<'gc>) -> bool {
         check_eq_impl(a, b, &mut Default::default())
     }
+    pub(super) fn check_identical<'gc, 'a>(a: &'a Value<'gc>, b: &'a Value<'gc>) -> bool {
+        match (a, b) {
+            (Value::Bool(_) | Value::Number(_) | Value::String(_), _) | (_, Value::Bool(_) | Value::Number(_) | Value::String(_)) => check_eq(a, b),
+            _ => a.identity() == b.identity(),
+        }
+    }
 }
@@ -2,6 +2,7 @@ use std::prelude::v1::*;
 use std::collections::VecDeque;
 use std::rc::Rc;
 use std::iter;
+use std::mem;
 
 use crate::*;
 use crate::gc::*;
@@ -32,6 +33,50 @@ pub enum ProjectStep {
     Normal,
     /// There were no running processes to execute.
     Idle,
+    /// Every running process is blocked on an asynchronous operation, so no progress can be made until one of them completes.
+    /// Rather than continuing to step the project, hosts may block until the earliest [`WaitInfo::wake_time`] (if any)
+    /// or until the [`System`] signals that an operation has completed (see [`System::register_waker`]).
+    Waiting { info: WaitInfo },
+    /// A process terminated with an error.
+    /// The failing process is stopped, but other processes in the project are not affected.
+    Error { error: ExecError },
+}
+
+/// A summary of what a [`Project`] is blocked on (see [`ProjectStep::Waiting`]).
+#[derive(Debug, Default, Clone, PartialEq, Eq, Collect)]
+#[collect(require_static)]
+pub struct WaitInfo {
+    /// The number of processes awaiting the result of an RPC.
+    pub rpcs: usize,
+    /// The number of processes sleeping on a timer.
+    pub timers: usize,
+    /// The number of processes awaiting a [`Barrier`], such as from a "broadcast and wait" block.
+    pub barriers: usize,
+    /// The conditions awaited by those processes, which can be used to block until one of them completes (see [`BarrierCondition::register_waker`]).
+    pub conditions: Vec<BarrierCondition>,
+    /// The earliest time (in the units of [`System::time_ms`]) at which a sleeping process will wake up or a pending RPC will time out, if any.
+    pub wake_time: Option<u64>,
+}
+impl WaitInfo {
+    fn add(&mut self, reason: WaitReason) {
+        match reason {
+            WaitReason::Rpc { deadline } => {
+                self.rpcs += 1;
+                if let Some(deadline) = deadline { self.add_wake_time(deadline) }
+            }
+            WaitReason::Barrier { condition } => {
+                self.barriers += 1;
+                self.conditions.push(condition);
+            }
+            WaitReason::Timer { wake_time } => {
+                self.timers += 1;
+                self.add_wake_time(wake_time);
+            }
+        }
+    }
+    fn add_wake_time(&mut self, time: u64) {
+        self.wake_time = Some(self.wake_time.map(|x| x.min(time)).unwrap_or(time));
+    }
 }
 
 #[derive(Collect)]
@@ -42,6 +87,9 @@ struct State<'gc, S: System> {
     settings: Settings,
     processes: SlotMap<ProcessKey, Process<'gc, S>>,
     process_queue: VecDeque<ProcessKey>,
+    wait_streak: usize,
+    wait_info: WaitInfo,
+    orphaned_rpcs: Vec<S::RpcKey>,
 }
 #[derive(Collect)]
 #[collect(no_drop)]
@@ -92,7 +140,9 @@ impl<'gc> Script<'gc> {
     }
     fn stop_all<S: System>(&mut self, state: &mut State<'gc, S>) {
         if let Some(process) = self.process {
-            state.processes.remove(process);
+            if let Some(process) = state.processes.remove(process) {
+                state.orphaned_rpcs.extend(process.into_orphaned_rpcs());
+            }
             self.process = None;
         }
         self.context_queue.clear();
@@ -138,10 +188,15 @@ impl<'gc, S: System> Project<'gc, S> {
                 settings,
                 processes: Default::default(),
                 process_queue: Default::default(),
+                wait_streak: 0,
+                wait_info: Default::default(),
+                orphaned_rpcs: vec![],
             }
         }
     }
     pub fn input(&mut self, input: Input) {
+        self.state.wait_streak = 0;
+        self.state.wait_info = Default::default();
         match input {
             Input::Start => {
                 for script in self.scripts.iter_mut() {
@@ -152,12 +207,18 @@ impl<'gc, S: System> Project<'gc, S> {
                 }
             }
             Input::Stop => {
-                self.state.processes.clear();
+                for (_, process) in mem::take(&mut self.state.processes) {
+                    self.state.orphaned_rpcs.extend(process.into_orphaned_rpcs());
+                }
                 self.state.process_queue.clear();
             }
         }
     }
     pub fn step(&mut self, mc: MutationContext<'gc, '_>, system: &S) -> ProjectStep {
+        for key in self.state.orphaned_rpcs.drain(..) {
+            system.cancel_rpc(&key);
+        }
+
         let (proc_key, proc) = loop {
             match self.state.process_queue.pop_front() {
                 None => return ProjectStep::Idle,
@@ -165,10 +226,26 @@ impl<'gc, S: System> Project<'gc, S> {
             }
         };
 
-        match proc.step(mc, system) {
+        let res = proc.step(mc, system);
+        if let Ok(ProcessStep::Waiting { reason }) = &res {
+            self.state.wait_streak += 1;
+            self.state.wait_info.add(reason.clone());
+            self.state.process_queue.push_back(proc_key);
+
+            if self.state.wait_streak >= self.state.process_queue.len() {
+                self.state.wait_streak = 0;
+                return ProjectStep::Waiting { info: mem::take(&mut self.state.wait_info) };
+            }
+            return ProjectStep::Normal;
+        }
+        self.state.wait_streak = 0;
+        self.state.wait_info = Default::default();
+
+        match res {
             Ok(x) => match x {
                 ProcessStep::Normal => self.state.process_queue.push_front(proc_key),
                 ProcessStep::Yield => self.state.process_queue.push_back(proc_key),
+                ProcessStep::Waiting { .. } => unreachable!(),
                 ProcessStep::Terminate { .. } => (),
                 ProcessStep::Idle => unreachable!(),
                 ProcessStep::Broadcast { msg_type, barrier } => {
@@ -183,7 +260,7 @@ impl<'gc, S: System> Project<'gc, S> {
                     self.state.process_queue.push_front(proc_key); // keep executing same process, if it was a wait, it'll yield next step
                 }
             }
-            Err(_) => unimplemented!(),
+            Err(error) => return ProjectStep::Error { error },
         }
 
         ProjectStep::Normal
@@ -191,4 +268,29 @@ impl<'gc, S: System> Project<'gc, S> {
     pub fn global_context(&self) -> GcCell<'gc, GlobalContext<'gc>> {
         self.state.global_context
     }
+    /// Computes the approximate number of bytes used by the data which is still reachable by the project (see [`HeapCounter`]),
+    /// including global variables, entity fields, and the state of all processes.
+    /// After garbage collection, hosts should store this in [`GlobalContext::heap_usage`] so that freed memory no longer counts towards the heap size limit.
+    pub fn heap_size(&self) -> usize {
+        let mut counter = HeapCounter::new();
+        let global_context = self.state.global_context.read();
+        counter.add_symbols(&global_context.globals);
+        for entity in global_context.entities.iter() {
+            counter.add_value(&Value::Entity(*entity));
+        }
+        for script in self.scripts.iter() {
+            counter.add_value(&Value::Entity(script.entity));
+            for (locals, _) in script.context_queue.iter() {
+                counter.add_symbols(locals);
+            }
+        }
+        for (_, process) in self.state.processes.iter() {
+            process.count_heap(&mut counter);
+        }
+        counter.size()
+    }
+    /// Checks all RPC call sites in the project against the metadata provided by `system` (see [`ByteCode::check_rpcs`]).
+    pub fn check_rpcs(&self, system: &S) -> Vec<(usize, RpcCheckError)> {
+        self.state.code.check_rpcs(system)
+    }
 }
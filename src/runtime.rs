@@ -1,8 +1,12 @@
 use std::prelude::v1::*;
-use std::collections::{BTreeMap, BTreeSet};
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+use std::cell::RefCell;
 use std::marker::PhantomData;
 use std::rc::{Rc, Weak};
+use std::sync::Arc;
 use std::fmt;
+use std::iter;
+use std::task::Waker;
 
 use crate::*;
 use crate::gc::*;
@@ -17,12 +21,97 @@ pub enum ToJsonError {
     HadBadNumber(f64),
 }
 
+const BASE64_CHARS: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+fn base64_encode(data: &[u8]) -> String {
+    let mut res = String::with_capacity(data.len().div_ceil(3) * 4);
+    for chunk in data.chunks(3) {
+        let bits = (chunk[0] as u32) << 16 | (*chunk.get(1).unwrap_or(&0) as u32) << 8 | *chunk.get(2).unwrap_or(&0) as u32;
+        for i in 0..4 {
+            res.push(if i <= chunk.len() { BASE64_CHARS[(bits >> (18 - 6 * i)) as usize & 63] as char } else { '=' });
+        }
+    }
+    res
+}
+fn base64_decode(text: &str) -> Option<Vec<u8>> {
+    let mut res = Vec::with_capacity(text.len() / 4 * 3);
+    let (mut acc, mut bits) = (0u32, 0);
+    for c in text.bytes().filter(|c| !c.is_ascii_whitespace()) {
+        let v = match c {
+            b'A'..=b'Z' => c - b'A',
+            b'a'..=b'z' => c - b'a' + 26,
+            b'0'..=b'9' => c - b'0' + 52,
+            b'+' | b'-' => 62,
+            b'/' | b'_' => 63,
+            b'=' => continue,
+            _ => return None,
+        };
+        acc = (acc << 6) | v as u32;
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            res.push((acc >> bits) as u8);
+            acc &= (1 << bits) - 1;
+        }
+    }
+    Some(res)
+}
+
+/// Binary data tagged with a content (MIME) type, such as an image returned by an RPC.
+///
+/// Hosts can save [`Blob::data`] directly, or use [`Blob::content_type`] to decide how to display it.
+/// When passed to external code as [`Json`] (e.g., as an RPC argument), a blob is encoded as a base64 data url (see [`Blob::to_data_url`]).
+#[derive(Clone, PartialEq, Eq, Collect)]
+#[collect(require_static)]
+pub struct Blob {
+    /// The MIME type of the data, e.g., `"image/png"`.
+    pub content_type: String,
+    /// The raw binary content.
+    pub data: Vec<u8>,
+}
+impl fmt::Debug for Blob {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Blob({}, {} bytes)", self.content_type, self.data.len())
+    }
+}
+impl Blob {
+    /// Checks if this blob holds an image, based on its content type.
+    pub fn is_image(&self) -> bool {
+        self.content_type.starts_with("image/")
+    }
+    /// Encodes this blob as a data url of the form `data:<content type>;base64,<data>`.
+    pub fn to_data_url(&self) -> String {
+        format!("data:{};base64,{}", self.content_type, base64_encode(&self.data))
+    }
+    /// Parses a base64 data url of the form produced by [`Blob::to_data_url`], or returns [`None`] if the input is not of this form.
+    pub fn from_data_url(url: &str) -> Option<Self> {
+        let (meta, data) = url.strip_prefix("data:")?.split_once(',')?;
+        let content_type = meta.strip_suffix(";base64")?;
+        Some(Blob { content_type: content_type.to_owned(), data: base64_decode(data)? })
+    }
+}
+
+#[test]
+fn test_blob_data_url() {
+    for (data, encoded) in [(&b""[..], ""), (b"f", "Zg=="), (b"fo", "Zm8="), (b"foo", "Zm9v"), (b"foob", "Zm9vYg=="), (b"\xff\x00\xfe\x01", "/wD+AQ==")] {
+        let blob = Blob { content_type: "application/octet-stream".into(), data: data.to_vec() };
+        let url = blob.to_data_url();
+        assert_eq!(url, format!("data:application/octet-stream;base64,{encoded}"));
+        assert_eq!(Blob::from_data_url(&url).unwrap(), blob);
+    }
+    assert_eq!(Blob::from_data_url("data:image/png;base64,iVBO\nRw0K").unwrap().data, b"\x89PNG\r\n");
+    assert!(Blob::from_data_url("data:text/plain,hello").is_none());
+    assert!(Blob::from_data_url("data:image/png;base64,#").is_none());
+    assert!(Blob::from_data_url("hello").is_none());
+}
+
 #[derive(Debug, PartialEq)]
 pub enum SimpleValue {
     Bool(bool),
     Number(f64),
     String(String),
     List(Vec<SimpleValue>),
+    Blob(Blob),
 }
 impl SimpleValue {
     /// Retrieves the value of the [`SimpleValue::Bool`] variant, or [`None`] if that is not the current variant.
@@ -33,6 +122,8 @@ impl SimpleValue {
     pub fn as_str(&self) -> Option<&str> { match self { SimpleValue::String(x) => Some(x), _ => None } }
     /// Retrieves the value of the [`SimpleValue::List`] variant, or [`None`] if that is not the current variant.
     pub fn as_list(&self) -> Option<&[SimpleValue]> { match self { SimpleValue::List(x) => Some(x), _ => None } }
+    /// Retrieves the value of the [`SimpleValue::Blob`] variant, or [`None`] if that is not the current variant.
+    pub fn as_blob(&self) -> Option<&Blob> { match self { SimpleValue::Blob(x) => Some(x), _ => None } }
     /// Retrieves the value of the [`SimpleValue::String`] variant, or [`None`] if that is not the current variant.
     pub fn into_string(self) -> Option<String> { match self { SimpleValue::String(x) => Some(x), _ => None } }
     /// Retrieves the value of the [`SimpleValue::List`] variant, or [`None`] if that is not the current variant.
@@ -43,6 +134,7 @@ impl From<f64> for SimpleValue { fn from(v: f64) -> Self { Self::Number(v) } }
 impl From<i64> for SimpleValue { fn from(v: i64) -> Self { Self::Number(v as f64) } }
 impl From<String> for SimpleValue { fn from(v: String) -> Self { Self::String(v) } }
 impl From<Vec<SimpleValue>> for SimpleValue { fn from(v: Vec<SimpleValue>) -> Self { Self::List(v) } }
+impl From<Blob> for SimpleValue { fn from(v: Blob) -> Self { Self::Blob(v) } }
 impl TryFrom<Json> for SimpleValue {
     type Error = FromJsonError;
     /// Create a new [`SimpleValue`] from a [`Json`] value.
@@ -63,22 +155,101 @@ impl TryFrom<Json> for SimpleValue {
 }
 impl TryInto<Json> for SimpleValue {
     type Error = ToJsonError;
+    /// Convert a [`SimpleValue`] into [`Json`], using [`JsonObjectStrategy::TextKeys`] to decide which lists become objects.
+    /// See [`SimpleValue::into_json`] for details.
+    fn try_into(self) -> Result<Json, Self::Error> {
+        self.into_json(JsonObjectStrategy::TextKeys)
+    }
+}
+impl SimpleValue {
     /// Convert a [`SimpleValue`] into [`Json`].
     /// 
     /// [`Json`] does not allow numbers to be infinite or nan, which is the only failure case for this conversion.
-    fn try_into(self) -> Result<Json, Self::Error> {
+    /// Numbers which are exact integers are stored as JSON integers, so they serialize as in JavaScript (e.g., `4` rather than `4.0`).
+    /// [`Blob`] values are encoded as data url strings (see [`Blob::to_data_url`]).
+    /// Lists of `[key, value]` pairs are encoded as objects or arrays according to `strategy`,
+    /// which reverses the conversion of objects done by [`SimpleValue::try_from`].
+    pub fn into_json(self, strategy: JsonObjectStrategy) -> Result<Json, ToJsonError> {
         Ok(match self {
             SimpleValue::Bool(x) => Json::Bool(x),
+            SimpleValue::Number(x) if x.fract() == 0.0 && x.abs() <= 9007199254740992.0 => Json::from(x as i64),
             SimpleValue::Number(x) => match serde_json::Number::from_f64(x) {
                 Some(x) => Json::Number(x),
-                None => return Err(Self::Error::HadBadNumber(x)),
+                None => return Err(ToJsonError::HadBadNumber(x)),
             }
             SimpleValue::String(x) => Json::String(x),
-            SimpleValue::List(x) => Json::Array(x.into_iter().map(TryInto::try_into).collect::<Result<_,_>>()?),
+            SimpleValue::List(x) => match strategy.object_keys(&x) {
+                Some(keys) => Json::Object(iter::zip(keys, x).map(|(k, pair)| Ok((k, pair.into_list().unwrap().pop().unwrap().into_json(strategy)?))).collect::<Result<_,_>>()?),
+                None => Json::Array(x.into_iter().map(|x| x.into_json(strategy)).collect::<Result<_,_>>()?),
+            }
+            SimpleValue::Blob(x) => Json::String(x.to_data_url()),
         })
     }
 }
 
+/// Decides which lists are encoded as JSON objects rather than arrays (see [`SimpleValue::into_json`]).
+/// 
+/// NetsBlox represents structured data (e.g., JSON objects) as a list of `[key, value]` pairs.
+/// However, such a list could also just be a table which happens to have two columns, so the conversion back to JSON is ambiguous.
+/// In every strategy, a list is only encoded as an object if it is non-empty, every item is a list of exactly two items, and the keys are distinct.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JsonObjectStrategy {
+    /// Lists are always encoded as arrays.
+    Never,
+    /// Lists of pairs are encoded as objects if every key is text which is not a number.
+    /// This keeps numeric tables (e.g., from parsing CSV) as arrays.
+    TextKeys,
+    /// Lists of pairs are encoded as objects if every key is text or a number.
+    AnyKeys,
+}
+impl JsonObjectStrategy {
+    /// Gets the object keys to use for `items`, or [`None`] if it should be encoded as an array.
+    fn object_keys(self, items: &[SimpleValue]) -> Option<Vec<String>> {
+        if self == JsonObjectStrategy::Never || items.is_empty() { return None }
+
+        let mut keys = Vec::with_capacity(items.len());
+        let mut seen = BTreeSet::new();
+        for item in items {
+            let key = match item.as_list()? {
+                [key, _] => match (key, self) {
+                    (SimpleValue::String(x), JsonObjectStrategy::AnyKeys) => x.clone(),
+                    (SimpleValue::String(x), _) if x.parse::<f64>().is_err() => x.clone(),
+                    (SimpleValue::Number(x), JsonObjectStrategy::AnyKeys) => number_to_string(*x),
+                    _ => return None,
+                }
+                _ => return None,
+            };
+            if !seen.insert(key.clone()) { return None }
+            keys.push(key);
+        }
+        Some(keys)
+    }
+}
+#[test]
+fn test_json_objects() {
+    let pairs = || simple_value!([["name", "Sarah"], ["pets", [["dog", 2], ["cat", 0]]], ["ids", [[1, 2], [3, 4]]]]);
+    let json = json!({ "name": "Sarah", "pets": { "dog": 2, "cat": 0 }, "ids": [[1, 2], [3, 4]] });
+    assert_eq!(pairs().into_json(JsonObjectStrategy::TextKeys).unwrap(), json);
+    assert_eq!(SimpleValue::try_from(json.clone()).unwrap().into_json(JsonObjectStrategy::TextKeys).unwrap(), json);
+    let sorted = SimpleValue::try_from(json).unwrap();
+    assert_eq!(sorted.as_list().unwrap().len(), 3);
+
+    assert_eq!(pairs().into_json(JsonObjectStrategy::Never).unwrap(), json!([["name", "Sarah"], ["pets", [["dog", 2], ["cat", 0]]], ["ids", [[1, 2], [3, 4]]]]));
+    assert_eq!(pairs().into_json(JsonObjectStrategy::AnyKeys).unwrap(), json!({ "name": "Sarah", "pets": { "dog": 2, "cat": 0 }, "ids": { "1": 2, "3": 4 } }));
+
+    let ambiguous = [
+        simple_value!([]),
+        simple_value!([["a", 1], ["a", 2]]),
+        simple_value!([["a", 1], ["b", 2, 3]]),
+        simple_value!([["a", 1], "b"]),
+        simple_value!([["1", "x"], ["2", "y"]]),
+    ];
+    for value in ambiguous {
+        assert!(value.into_json(JsonObjectStrategy::TextKeys).unwrap().is_array());
+    }
+    assert!(simple_value!([["1", "x"], ["2", "y"]]).into_json(JsonObjectStrategy::AnyKeys).unwrap().is_object());
+}
+
 /// Creates a new [`SimpleValue`] using Python-like syntax.
 /// 
 /// Python-style dictionary notation creates NetsBlox structured data, which is simply a list of key/value pairs.
@@ -183,10 +354,56 @@ fn test_simple_value_macro() {
     }
 }
 
+/// Converts a number to a string in the same way as JavaScript's `Number.prototype.toString`, which is what Snap uses to display numbers.
+///
+/// This uses the shortest representation which parses back to the same value,
+/// switching to exponential notation (e.g., `1e+21` or `1.5e-7`) for very large and very small magnitudes.
+pub fn number_to_string(value: f64) -> String {
+    if value.is_nan() { return "NaN".into() }
+    if value == 0.0 { return "0".into() } // includes negative zero
+    if value.is_infinite() { return if value > 0.0 { "Infinity" } else { "-Infinity" }.into() }
+
+    // rust's exponential format gives the shortest round-trip digits, which we then lay out like js
+    let sign = if value < 0.0 { "-" } else { "" };
+    let sci = format!("{:e}", value.abs());
+    let (mantissa, exp) = sci.split_once('e').unwrap();
+    let digits = mantissa.replace('.', "");
+    let k = digits.len() as i32;
+    let n = exp.parse::<i32>().unwrap() + 1; // value is 0.digits * 10^n
+
+    if k <= n && n <= 21 {
+        format!("{sign}{digits}{}", "0".repeat((n - k) as usize))
+    } else if 0 < n && n <= 21 {
+        format!("{sign}{}.{}", &digits[..n as usize], &digits[n as usize..])
+    } else if -6 < n && n <= 0 {
+        format!("{sign}0.{}{digits}", "0".repeat(-n as usize))
+    } else {
+        let exp_sign = if n > 0 { '+' } else { '-' };
+        match k {
+            1 => format!("{sign}{digits}e{exp_sign}{}", (n - 1).abs()),
+            _ => format!("{sign}{}.{}e{exp_sign}{}", &digits[..1], &digits[1..], (n - 1).abs()),
+        }
+    }
+}
+#[test]
+fn test_number_to_string() {
+    let cases: &[(f64, &str)] = &[
+        (0.0, "0"), (-0.0, "0"), (1.0, "1"), (-12.0, "-12"), (0.5, "0.5"), (-3.25, "-3.25"),
+        (0.1 + 0.2, "0.30000000000000004"), (1.0 / 3.0, "0.3333333333333333"), (123456.789, "123456.789"),
+        (1e20, "100000000000000000000"), (1e21, "1e+21"), (1.5e21, "1.5e+21"), (-2.5e300, "-2.5e+300"),
+        (0.000001, "0.000001"), (0.0000015, "0.0000015"), (1e-7, "1e-7"), (1.5e-7, "1.5e-7"), (5e-324, "5e-324"),
+        (f64::MAX, "1.7976931348623157e+308"), (9007199254740993.0, "9007199254740992"),
+        (f64::NAN, "NaN"), (f64::INFINITY, "Infinity"), (f64::NEG_INFINITY, "-Infinity"),
+    ];
+    for (value, expected) in cases {
+        assert_eq!(number_to_string(*value), *expected);
+    }
+}
+
 /// The type of a [`Value`].
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum Type {
-    Bool, Number, String, List, Closure, Entity,
+    Bool, Number, String, List, Closure, Entity, Blob,
 }
 
 /// A type conversion error on a [`Value`].
@@ -227,16 +444,19 @@ pub enum Value<'gc> {
     Closure(GcCell<'gc, Closure<'gc>>),
     /// A reference to an [`Entity`] in the environment.
     Entity(GcCell<'gc, Entity<'gc>>),
+    /// Immutable binary data with a content type, such as an image.
+    Blob(Gc<'gc, Blob>),
 }
 impl fmt::Debug for Value<'_> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         fn print<'gc>(value: &Value<'gc>, cache: &mut BTreeSet<Identity<'gc>>, f: &mut fmt::Formatter<'_>) -> fmt::Result {
             match value {
                 Value::Bool(x) => write!(f, "{x}"),
-                Value::Number(x) => write!(f, "{x}"),
+                Value::Number(x) => write!(f, "{}", number_to_string(*x)),
                 Value::String(x) => write!(f, "{:?}", x.as_str()),
                 Value::Closure(x) => write!(f, "{:?}", &*x.read()),
                 Value::Entity(x) => write!(f, "{:?}", &*x.read()),
+                Value::Blob(x) => write!(f, "{:?}", &**x),
                 Value::List(x) => {
                     let identity = value.identity();
                     if !cache.insert(identity) { return write!(f, "[...]") }
@@ -267,6 +487,7 @@ impl<'gc> From<Gc<'gc, String>> for Value<'gc> { fn from(v: Gc<'gc, String>) ->
 impl<'gc> From<GcCell<'gc, Vec<Value<'gc>>>> for Value<'gc> { fn from(v: GcCell<'gc, Vec<Value<'gc>>>) -> Self { Value::List(v) } }
 impl<'gc> From<GcCell<'gc, Closure<'gc>>> for Value<'gc> { fn from(v: GcCell<'gc, Closure<'gc>>) -> Self { Value::Closure(v) } }
 impl<'gc> From<GcCell<'gc, Entity<'gc>>> for Value<'gc> { fn from(v: GcCell<'gc, Entity<'gc>>) -> Self { Value::Entity(v) } }
+impl<'gc> From<Gc<'gc, Blob>> for Value<'gc> { fn from(v: Gc<'gc, Blob>) -> Self { Value::Blob(v) } }
 impl<'gc> Value<'gc> {
     /// Creates a new value from an abstract syntax tree.
     pub fn from_ast(mc: MutationContext<'gc, '_>, value: &ast::Value) -> Self {
@@ -286,6 +507,7 @@ impl<'gc> Value<'gc> {
             SimpleValue::Number(x) => Value::Number(x),
             SimpleValue::String(x) => Value::String(Gc::allocate(mc, x)),
             SimpleValue::List(x) => Value::List(GcCell::allocate(mc, x.into_iter().map(|x| Value::from_simple(mc, x)).collect())),
+            SimpleValue::Blob(x) => Value::Blob(Gc::allocate(mc, x)),
         }
     }
     pub fn to_simple(&self) -> Result<SimpleValue, SimplifyError> {
@@ -294,6 +516,7 @@ impl<'gc> Value<'gc> {
                 Value::Bool(x) => SimpleValue::Bool(*x),
                 Value::Number(x) => SimpleValue::Number(*x),
                 Value::String(x) => SimpleValue::String(x.as_str().to_owned()),
+                Value::Blob(x) => SimpleValue::Blob((**x).clone()),
                 Value::Closure(_) | Value::Entity(_) => return Err(SimplifyError::HadComplexType(value.get_type())),
                 Value::List(x) => {
                     let identity = value.identity();
@@ -318,6 +541,7 @@ impl<'gc> Value<'gc> {
             Value::String(x) => x.into(),
             Value::Entity(x) => x.into(),
             Value::Closure(x) => x.into(),
+            Value::Blob(x) => x.into(),
             Value::List(x) => GcCell::allocate(mc, x.read().to_owned()).into(),
         }
     }
@@ -332,6 +556,7 @@ impl<'gc> Value<'gc> {
             Value::List(x) => Identity(x.as_ptr() as *const Vec<Value> as *const (), PhantomData),
             Value::Closure(x) => Identity(x.as_ptr() as *const Closure as *const (), PhantomData),
             Value::Entity(x) => Identity(x.as_ptr() as *const Entity as *const (), PhantomData),
+            Value::Blob(x) => Identity(Gc::as_ptr(*x) as *const (), PhantomData),
         }
     }
     /// Gets the type of value that is stored.
@@ -343,6 +568,7 @@ impl<'gc> Value<'gc> {
             Value::List(_) => Type::List,
             Value::Closure(_) => Type::Closure,
             Value::Entity(_) => Type::Entity,
+            Value::Blob(_) => Type::Blob,
         }
     }
     /// Attempts to interpret this value as a bool.
@@ -365,7 +591,7 @@ impl<'gc> Value<'gc> {
     pub fn to_string(&self, mc: MutationContext<'gc, '_>) -> Result<Gc<'gc, String>, ConversionError> {
         Ok(match self {
             Value::String(x) => *x,
-            Value::Number(x) => Gc::allocate(mc, x.to_string()),
+            Value::Number(x) => Gc::allocate(mc, number_to_string(*x)),
             x => return Err(ConversionError { got: x.get_type(), expected: Type::String }),
         })
     }
@@ -383,6 +609,13 @@ impl<'gc> Value<'gc> {
             x => Err(ConversionError { got: x.get_type(), expected: Type::Closure }.into()),
         }
     }
+    /// Attempts to interpret this value as a blob.
+    pub fn as_blob(&self) -> Result<Gc<'gc, Blob>, ConversionError> {
+        match self {
+            Value::Blob(x) => Ok(*x),
+            x => Err(ConversionError { got: x.get_type(), expected: Type::Blob }),
+        }
+    }
     /// Attempts to interpret this value as an entity.
     pub fn as_entity(&self) -> Result<GcCell<'gc, Entity<'gc>>, ConversionError> {
         match self {
@@ -399,6 +632,8 @@ pub struct Closure<'gc> {
     pub pos: usize,
     pub params: Vec<String>,
     pub captures: SymbolTable<'gc>,
+    /// The number of empty input slots in the closure's code, which act as implicit parameters. This is always zero if there are `params`.
+    pub empty_slots: usize,
 }
 impl fmt::Debug for Closure<'_> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
@@ -581,6 +816,11 @@ pub struct GlobalContext<'gc> {
     pub proj_name: String,
     pub globals: SymbolTable<'gc>,
     pub entities: Vec<GcCell<'gc, Entity<'gc>>>,
+    /// An approximation of the number of bytes allocated by the program, which is used to enforce the heap size limit in [`Settings`](crate::process::Settings).
+    /// This value only ever increases during execution, so it counts every allocation rather than the live heap.
+    /// After garbage collection, the host should reset it to the size of the data that is still reachable (see [`Project::heap_size`](crate::project::Project::heap_size)),
+    /// as done by [`Executor::step`](crate::executor::Executor::step); otherwise, a heap size limit will eventually be exceeded by any long-running program.
+    pub heap_usage: usize,
 }
 impl<'gc> GlobalContext<'gc> {
     pub fn from_ast(mc: MutationContext<'gc, '_>, role: &ast::Role) -> Self {
@@ -592,6 +832,54 @@ impl<'gc> GlobalContext<'gc> {
                 fields: SymbolTable::from_ast(mc, &entity.fields),
                 alive: true,
             })).collect(),
+            heap_usage: 0,
+        }
+    }
+}
+
+/// Computes the approximate number of bytes used by the strings and lists reachable from a collection of values.
+///
+/// This uses the same estimate as the heap size limit in [`Settings`](crate::process::Settings),
+/// but shared reference types are only counted once, no matter how many times they are encountered.
+#[derive(Default)]
+pub struct HeapCounter<'gc> {
+    visited: BTreeSet<Identity<'gc>>,
+    size: usize,
+}
+impl<'gc> HeapCounter<'gc> {
+    /// Creates a new counter with a size of zero.
+    pub fn new() -> Self {
+        Default::default()
+    }
+    /// The total size of all the values counted so far.
+    pub fn size(&self) -> usize {
+        self.size
+    }
+    /// Counts the given value and everything reachable from it.
+    pub fn add_value(&mut self, value: &Value<'gc>) {
+        match value {
+            Value::Bool(_) | Value::Number(_) => return,
+            _ => if !self.visited.insert(value.identity()) { return },
+        }
+        match value {
+            Value::Bool(_) | Value::Number(_) => (),
+            Value::String(x) => self.size = self.size.saturating_add(x.len()),
+            Value::Blob(x) => self.size = self.size.saturating_add(x.data.len()),
+            Value::List(x) => {
+                let x = x.read();
+                self.size = self.size.saturating_add(x.len().saturating_mul(std::mem::size_of::<Value>()));
+                for item in x.iter() {
+                    self.add_value(item);
+                }
+            }
+            Value::Closure(x) => self.add_symbols(&x.read().captures),
+            Value::Entity(x) => self.add_symbols(&x.read().fields),
+        }
+    }
+    /// Counts the values of all the variables in a symbol table.
+    pub fn add_symbols(&mut self, symbols: &SymbolTable<'gc>) {
+        for (_, value) in symbols.iter() {
+            self.add_value(&value.get());
         }
     }
 }
@@ -599,28 +887,52 @@ impl<'gc> GlobalContext<'gc> {
 /// A blocking handle for a [`BarrierCondition`].
 #[derive(Debug, Clone, Collect)]
 #[collect(require_static)]
-pub struct Barrier(Rc<()>);
+pub struct Barrier(Rc<BarrierHandle>);
+#[derive(Debug)]
+struct BarrierHandle(Rc<RefCell<Option<Waker>>>);
+impl Drop for BarrierHandle {
+    fn drop(&mut self) {
+        if let Some(waker) = self.0.borrow_mut().take() { waker.wake() }
+    }
+}
 /// Waits for the destruction of all associated [`Barrier`] handles.
 #[derive(Debug, Clone)]
-pub struct BarrierCondition(Weak<()>);
+pub struct BarrierCondition {
+    handle: Weak<BarrierHandle>,
+    waker: Rc<RefCell<Option<Waker>>>,
+}
 impl Barrier {
     /// Creates a new [`Barrier`] which is not related to any other barrier.
     /// A barrier can be cloned to create additional associated, blocking handles for the same condition.
     pub fn new() -> Self {
-        Barrier(Rc::new(()))
+        Barrier(Rc::new(BarrierHandle(Default::default())))
     }
     /// Constructs a [`BarrierCondition`] object which waits for this barrier handle and all of its associated handles
     /// (created before or after this point) to be destroyed.
     pub fn get_condition(&self) -> BarrierCondition {
-        BarrierCondition(Rc::downgrade(&self.0))
+        BarrierCondition { handle: Rc::downgrade(&self.0), waker: self.0.0.clone() }
     }
 }
 impl BarrierCondition {
     /// Checks if the condition has been completed, i.e., that all the associated barriers have been destroyed.
     pub fn is_completed(&self) -> bool {
-        self.0.strong_count() == 0
+        self.handle.strong_count() == 0
+    }
+    /// Registers a [`Waker`] to be woken when the condition is completed, replacing any previously registered waker.
+    /// If the condition is already completed, the waker is woken immediately.
+    pub fn register_waker(&self, waker: &Waker) {
+        match self.is_completed() {
+            true => waker.wake_by_ref(),
+            false => *self.waker.borrow_mut() = Some(waker.clone()),
+        }
     }
 }
+impl PartialEq for BarrierCondition {
+    fn eq(&self, other: &Self) -> bool {
+        Weak::ptr_eq(&self.handle, &other.handle)
+    }
+}
+impl Eq for BarrierCondition {}
 
 /// The result of a successful call to [`System::poll_async`].
 pub enum AsyncPoll<T> {
@@ -630,10 +942,25 @@ pub enum AsyncPoll<T> {
     Pending,
 }
 
+/// The successful result of an RPC call (see [`System::poll_rpc`]).
+#[derive(Debug, Clone, PartialEq)]
+pub enum RpcResponse {
+    /// A structured result, which includes plain text results as [`Json::String`].
+    Json(Json),
+    /// A binary result, such as an image.
+    Blob(Blob),
+}
+impl From<Json> for RpcResponse { fn from(v: Json) -> Self { Self::Json(v) } }
+impl From<Blob> for RpcResponse { fn from(v: Blob) -> Self { Self::Blob(v) } }
+
 /// Types of [`System`] resources, grouped into feature categories.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum SystemFeature {
     Time,
+    Rpc,
+    Storage,
+    Files,
+    Random,
 }
 /// An error resulting from improper use of [`System`] resources.
 #[derive(Debug)]
@@ -644,6 +971,212 @@ pub enum SystemError {
     Other { description: String },
 }
 
+/// Describes a single argument of an RPC in [`ServiceMetadata`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RpcArgMetadata {
+    /// The name of the argument.
+    pub name: String,
+    /// The name of the argument's type as reported by the server (e.g., `"Number"`), or [`None`] if untyped.
+    /// Only some types are checked by [`ServiceMetadata::check_call`]; others are accepted as-is.
+    pub ty: Option<String>,
+    /// If `true`, the argument may be omitted or left empty.
+    pub optional: bool,
+}
+impl RpcArgMetadata {
+    fn accepts(&self, value: &Json) -> bool {
+        let as_number = |value: &Json| match value {
+            Json::Number(x) => x.as_f64(),
+            Json::String(x) => x.trim().parse::<f64>().ok(),
+            _ => None,
+        };
+        match self.ty.as_deref() {
+            Some("Number" | "BoundedNumber" | "Latitude" | "Longitude") => as_number(value).is_some(),
+            Some("Integer" | "BoundedInteger") => as_number(value).map(|x| x.fract() == 0.0).unwrap_or(false),
+            Some("Boolean") => matches!(value, Json::Bool(_)) || matches!(value.as_str(), Some("true" | "false")),
+            Some("Array") => value.is_array(),
+            _ => true,
+        }
+    }
+}
+/// Describes a single RPC in [`ServiceMetadata`].
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct RpcMetadata {
+    /// The arguments accepted by the RPC, in order.
+    pub args: Vec<RpcArgMetadata>,
+}
+/// A description of the RPCs provided by a service, which is used to validate RPC calls (see [`System::service_metadata`]).
+///
+/// This can be parsed from the service description format used by the NetsBlox server (see [`ServiceMetadata::from_json`]).
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct ServiceMetadata {
+    /// The RPCs provided by the service, keyed by name.
+    pub rpcs: BTreeMap<String, RpcMetadata>,
+}
+/// An error from parsing or loading [`ServiceMetadata`].
+#[derive(Debug)]
+pub enum ServiceMetadataError {
+    /// The metadata was not in the expected format.
+    BadFormat { description: String },
+    /// Failed to read the metadata file.
+    Io { description: String },
+}
+/// A structured error from checking an RPC call against [`ServiceMetadata`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RpcCheckError {
+    /// The service does not have an RPC with the given name.
+    UnknownRpc { service: String, rpc: String },
+    /// The RPC does not have an argument with the given name.
+    UnknownArg { service: String, rpc: String, arg: String },
+    /// A required argument was not given or was left empty.
+    MissingArg { service: String, rpc: String, arg: String },
+    /// An argument was given a value which could not be interpreted as the `expected` type.
+    ArgType { service: String, rpc: String, arg: String, expected: String },
+}
+impl ServiceMetadata {
+    /// Parses the description of a single service in the format returned by the NetsBlox server's `/services/<name>` endpoint.
+    /// That is, an object with an `rpcs` field holding an object of RPC descriptions keyed by name,
+    /// each with an `args` array of objects with fields `name`, `optional` (default `false`), and `type` (an object with a `name` field).
+    pub fn from_json(value: &Json) -> Result<Self, ServiceMetadataError> {
+        let bad_format = |description: &str| ServiceMetadataError::BadFormat { description: description.to_owned() };
+
+        let mut res = ServiceMetadata::default();
+        let rpcs = value.get("rpcs").and_then(Json::as_object).ok_or_else(|| bad_format("expected an object field 'rpcs'"))?;
+        for (rpc_name, rpc) in rpcs {
+            let mut args = vec![];
+            for arg in rpc.get("args").and_then(Json::as_array).ok_or_else(|| bad_format("expected an array field 'args'"))? {
+                args.push(RpcArgMetadata {
+                    name: arg.get("name").and_then(Json::as_str).ok_or_else(|| bad_format("expected a string field 'name'"))?.to_owned(),
+                    ty: arg.get("type").and_then(|x| x.get("name")).and_then(Json::as_str).map(str::to_owned),
+                    optional: arg.get("optional").and_then(Json::as_bool).unwrap_or(false),
+                });
+            }
+            res.rpcs.insert(rpc_name.clone(), RpcMetadata { args });
+        }
+        Ok(res)
+    }
+    /// Parses a collection of service descriptions, given as an object mapping service names to descriptions (see [`ServiceMetadata::from_json`]).
+    pub fn parse_all(value: &Json) -> Result<BTreeMap<String, ServiceMetadata>, ServiceMetadataError> {
+        let services = value.as_object().ok_or_else(|| ServiceMetadataError::BadFormat { description: "expected an object of services".into() })?;
+        services.iter().map(|(name, desc)| Ok((name.clone(), ServiceMetadata::from_json(desc)?))).collect()
+    }
+    /// Loads a collection of service descriptions from a JSON file (see [`ServiceMetadata::parse_all`]).
+    /// This requires the [`std`](crate) feature flag.
+    #[cfg(any(test, feature = "std"))]
+    pub fn load_all(path: &str) -> Result<BTreeMap<String, ServiceMetadata>, ServiceMetadataError> {
+        extern crate std as real_std;
+        let content = real_std::fs::read_to_string(path).map_err(|e| ServiceMetadataError::Io { description: e.to_string() })?;
+        let value = serde_json::from_str(&content).map_err(|e| ServiceMetadataError::BadFormat { description: e.to_string() })?;
+        Self::parse_all(&value)
+    }
+    /// Checks the argument names of a call to `rpc` on `service` (which this metadata describes).
+    /// This can be performed before execution, as it does not depend on argument values (see [`ByteCode::check_rpcs`](crate::bytecode::ByteCode::check_rpcs)).
+    pub fn check_arg_names(&self, service: &str, rpc: &str, args: &[&str]) -> Result<(), RpcCheckError> {
+        let meta = self.rpcs.get(rpc).ok_or_else(|| RpcCheckError::UnknownRpc { service: service.to_owned(), rpc: rpc.to_owned() })?;
+        for &arg in args {
+            if !meta.args.iter().any(|x| x.name == arg) {
+                return Err(RpcCheckError::UnknownArg { service: service.to_owned(), rpc: rpc.to_owned(), arg: arg.to_owned() });
+            }
+        }
+        for arg in meta.args.iter() {
+            if !arg.optional && !args.contains(&arg.name.as_str()) {
+                return Err(RpcCheckError::MissingArg { service: service.to_owned(), rpc: rpc.to_owned(), arg: arg.name.clone() });
+            }
+        }
+        Ok(())
+    }
+    /// Checks a call to `rpc` on `service` (which this metadata describes) with the given argument values.
+    /// In addition to the checks of [`ServiceMetadata::check_arg_names`], this requires that required arguments are not empty
+    /// and that arguments of numeric, boolean, and array types have values that can be interpreted as such.
+    pub fn check_call(&self, service: &str, rpc: &str, args: &[(String, Json)]) -> Result<(), RpcCheckError> {
+        self.check_arg_names(service, rpc, &args.iter().map(|x| x.0.as_str()).collect::<Vec<_>>())?;
+        for arg in self.rpcs[rpc].args.iter() {
+            let value = match args.iter().find(|x| x.0 == arg.name) {
+                Some(x) => &x.1,
+                None => continue,
+            };
+            if value.as_str() == Some("") {
+                if arg.optional { continue }
+                return Err(RpcCheckError::MissingArg { service: service.to_owned(), rpc: rpc.to_owned(), arg: arg.name.clone() });
+            }
+            if !arg.accepts(value) {
+                return Err(RpcCheckError::ArgType { service: service.to_owned(), rpc: rpc.to_owned(), arg: arg.name.clone(), expected: arg.ty.clone().unwrap_or_default() });
+            }
+        }
+        Ok(())
+    }
+}
+
+/// An error from checking an RPC call against [`RpcPermissions`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RpcPermissionError {
+    /// The RPC is not allowed to be called.
+    Denied { service: String, rpc: String },
+    /// The service has already been called `max_calls` times within the last `period` (in the units of [`System::time_ms`]).
+    RateLimited { service: String, rpc: String, max_calls: usize, period: u64 },
+}
+
+struct RateLimit {
+    max_calls: usize,
+    period: u64,
+    history: VecDeque<u64>,
+}
+
+/// Restricts which RPCs a program may call, as configured by [`Settings`](crate::process::Settings).
+///
+/// Rules are given as a service name and an optional RPC name, where [`None`] matches every RPC of the service.
+/// Calls matching a deny rule are always rejected. If there are any allow rules, calls which do not match one of them are rejected as well.
+/// Services may also have a rate limit, which is shared by all processes using the same [`RpcPermissions`].
+/// Rejected calls are never passed to [`System::call_rpc`].
+#[derive(Default)]
+pub struct RpcPermissions {
+    allowed: Option<Vec<(String, Option<String>)>>,
+    denied: Vec<(String, Option<String>)>,
+    rate_limits: RefCell<BTreeMap<String, RateLimit>>,
+}
+impl RpcPermissions {
+    /// Creates a new [`RpcPermissions`] which allows every RPC.
+    pub fn new() -> Self {
+        Default::default()
+    }
+    /// Allows calling the given RPC, or every RPC of `service` if `rpc` is [`None`].
+    /// Once any RPC is allowed, all other RPCs are denied.
+    pub fn allow(mut self, service: &str, rpc: Option<&str>) -> Self {
+        self.allowed.get_or_insert_with(Vec::new).push((service.to_owned(), rpc.map(str::to_owned)));
+        self
+    }
+    /// Denies calling the given RPC, or every RPC of `service` if `rpc` is [`None`].
+    /// This takes priority over [`RpcPermissions::allow`].
+    pub fn deny(mut self, service: &str, rpc: Option<&str>) -> Self {
+        self.denied.push((service.to_owned(), rpc.map(str::to_owned)));
+        self
+    }
+    /// Limits `service` to at most `max_calls` RPC calls within any window of `period` (in the units of [`System::time_ms`]).
+    pub fn rate_limit(self, service: &str, max_calls: usize, period: u64) -> Self {
+        self.rate_limits.borrow_mut().insert(service.to_owned(), RateLimit { max_calls, period, history: Default::default() });
+        self
+    }
+    /// Checks if the given RPC may be called, and if so, records the call for the purpose of rate limiting.
+    /// The current time is only requested if `service` has a rate limit.
+    pub fn check(&self, service: &str, rpc: &str, time_ms: impl FnOnce() -> Result<u64, SystemError>) -> Result<Result<(), RpcPermissionError>, SystemError> {
+        let matches = |rules: &[(String, Option<String>)]| rules.iter().any(|(s, r)| s == service && r.as_deref().map(|r| r == rpc).unwrap_or(true));
+        if matches(&self.denied) || !self.allowed.as_deref().map(matches).unwrap_or(true) {
+            return Ok(Err(RpcPermissionError::Denied { service: service.to_owned(), rpc: rpc.to_owned() }));
+        }
+
+        if let Some(limit) = self.rate_limits.borrow_mut().get_mut(service) {
+            let now = time_ms()?;
+            while limit.history.front().map(|&t| now.saturating_sub(t) >= limit.period).unwrap_or(false) {
+                limit.history.pop_front();
+            }
+            if limit.history.len() >= limit.max_calls {
+                return Ok(Err(RpcPermissionError::RateLimited { service: service.to_owned(), rpc: rpc.to_owned(), max_calls: limit.max_calls, period: limit.period }));
+            }
+            limit.history.push_back(now);
+        }
+        Ok(Ok(()))
+    }
+}
+
 /// Represents all the features of an implementing system.
 /// 
 /// This type encodes any features that cannot be performed without platform-specific resources.
@@ -658,22 +1191,263 @@ pub trait System: 'static {
     /// This is not required to represent the actual real-world time; e.g., this could simply measure uptime.
     /// Subsequent values are required to be non-decreasing.
     fn time_ms(&self) -> Result<u64, SystemError>;
+    /// Gets a random number uniformly distributed in the range `[0, 1)`.
+    /// The default implementation returns [`SystemError::NotSupported`] with [`SystemFeature::Random`].
+    fn random(&self) -> Result<f64, SystemError> {
+        Err(SystemError::NotSupported { feature: SystemFeature::Random })
+    }
 
     /// Requests the system to execute the given RPC.
     /// Returns a key that can be passed to [`System::poll_rpc`] to poll for the result.
     fn call_rpc(&self, service: String, rpc: String, args: Vec<(String, Json)>) -> Result<Self::RpcKey, SystemError>;
     /// Polls for the completion of an RPC call.
     /// If [`AsyncPoll::Completed`] is returned, the system is allowed to invalidate the requested `key`, which will not be used again.
-    fn poll_rpc(&self, key: &Self::RpcKey) -> Result<AsyncPoll<Result<Json, String>>, SystemError>;
+    fn poll_rpc(&self, key: &Self::RpcKey) -> Result<AsyncPoll<Result<RpcResponse, String>>, SystemError>;
+    /// Cancels a pending RPC call, e.g., because it timed out or the process awaiting it was stopped.
+    /// The system should release any resources associated with `key`, which will not be used again.
+    /// Any result that arrives after this point should be discarded.
+    /// The default implementation does nothing.
+    fn cancel_rpc(&self, _key: &Self::RpcKey) {}
+
+    /// Registers a waker which the system should wake (once) when any pending asynchronous operation (e.g., an RPC) completes.
+    /// If an operation has already completed but has not yet been polled, the waker should be woken immediately.
+    /// Only the most recently registered waker needs to be retained.
+    ///
+    /// This allows hosts to block while a project is waiting (see [`ProjectStep::Waiting`](crate::project::ProjectStep::Waiting)) rather than busy-polling.
+    /// Timers are not covered by this method, as hosts can compute the next deadline themselves from [`System::time_ms`].
+    /// The default implementation wakes the waker immediately, which is always correct but makes waiting hosts poll continuously.
+    fn register_waker(&self, waker: &Waker) {
+        waker.wake_by_ref();
+    }
+
+    /// Gets the [`ServiceMetadata`] for the given service, or [`None`] if it is not known.
+    /// If present, RPC calls to this service are checked against the metadata before being sent (see [`ServiceMetadata::check_call`]).
+    /// The default implementation knows no services and always returns [`None`].
+    fn service_metadata(&self, _service: &str) -> Option<Arc<ServiceMetadata>> {
+        None
+    }
+
+    /// Gets the persistent key-value [`Storage`] provided by this system, such as for cloud variables.
+    /// Systems without storage should return [`SystemError::NotSupported`] with [`SystemFeature::Storage`], which is the default.
+    fn storage(&self) -> Result<&dyn Storage, SystemError> {
+        Err(SystemError::NotSupported { feature: SystemFeature::Storage })
+    }
+
+    /// Reads the entire content of a text file.
+    /// Paths are relative to a root directory chosen by the system, which should not allow access to files outside of it (see [`FileSandbox`]).
+    /// Systems without file access should return [`SystemError::NotSupported`] with [`SystemFeature::Files`], as should the other file methods.
+    /// This is the default for all of them.
+    fn read_file(&self, _path: &str) -> Result<String, SystemError> {
+        Err(SystemError::NotSupported { feature: SystemFeature::Files })
+    }
+    /// Writes a text file, replacing any previous content and creating parent directories as needed.
+    fn write_file(&self, _path: &str, _content: &str) -> Result<(), SystemError> {
+        Err(SystemError::NotSupported { feature: SystemFeature::Files })
+    }
+    /// Lists the names of the entries of a directory in ascending order, where the names of subdirectories end with `/`.
+    fn list_dir(&self, _path: &str) -> Result<Vec<String>, SystemError> {
+        Err(SystemError::NotSupported { feature: SystemFeature::Files })
+    }
+}
+
+mod storage {
+    use std::cell::RefCell;
+
+    use super::*;
+
+    /// A persistent key-value store, partitioned into namespaces (see [`System::storage`]).
+    pub trait Storage {
+        /// Gets the value stored under `key` in `namespace`, or [`None`] if there is no such entry.
+        fn get(&self, namespace: &str, key: &str) -> Result<Option<Json>, SystemError>;
+        /// Stores `value` under `key` in `namespace`, replacing any previous value.
+        fn set(&self, namespace: &str, key: &str, value: Json) -> Result<(), SystemError>;
+        /// Deletes the entry for `key` in `namespace`, and returns `true` if there was such an entry.
+        fn delete(&self, namespace: &str, key: &str) -> Result<bool, SystemError>;
+        /// Lists all the keys in `namespace` in ascending order.
+        fn list(&self, namespace: &str) -> Result<Vec<String>, SystemError>;
+    }
+
+    /// A [`Storage`] implementation which keeps all data in memory, e.g., for testing.
+    #[derive(Default)]
+    pub struct MemoryStorage {
+        namespaces: RefCell<BTreeMap<String, BTreeMap<String, Json>>>,
+    }
+    impl MemoryStorage {
+        /// Creates a new, empty [`MemoryStorage`].
+        pub fn new() -> Self {
+            Default::default()
+        }
+    }
+    impl Storage for MemoryStorage {
+        fn get(&self, namespace: &str, key: &str) -> Result<Option<Json>, SystemError> {
+            Ok(self.namespaces.borrow().get(namespace).and_then(|x| x.get(key)).cloned())
+        }
+        fn set(&self, namespace: &str, key: &str, value: Json) -> Result<(), SystemError> {
+            self.namespaces.borrow_mut().entry(namespace.to_owned()).or_default().insert(key.to_owned(), value);
+            Ok(())
+        }
+        fn delete(&self, namespace: &str, key: &str) -> Result<bool, SystemError> {
+            Ok(self.namespaces.borrow_mut().get_mut(namespace).map(|x| x.remove(key).is_some()).unwrap_or(false))
+        }
+        fn list(&self, namespace: &str) -> Result<Vec<String>, SystemError> {
+            Ok(self.namespaces.borrow().get(namespace).map(|x| x.keys().cloned().collect()).unwrap_or_default())
+        }
+    }
+
+    /// A [`Storage`] implementation which persists data in a directory, with one JSON file per namespace.
+    /// This requires the [`std`](crate) feature flag.
+    #[cfg(any(test, feature = "std"))]
+    pub struct FileStorage {
+        root: String,
+    }
+    #[cfg(any(test, feature = "std"))]
+    impl FileStorage {
+        /// Creates a new [`FileStorage`] which stores data in the `root` directory.
+        /// The directory is created when data is first stored, if it does not already exist.
+        pub fn new(root: &str) -> Self {
+            Self { root: root.to_owned() }
+        }
+        fn path(&self, namespace: &str) -> String {
+            let mut name = String::with_capacity(namespace.len());
+            for b in namespace.bytes() {
+                match b {
+                    b'a'..=b'z' | b'A'..=b'Z' | b'0'..=b'9' | b'-' | b'_' | b'.' if !(b == b'.' && name.is_empty()) => name.push(b as char),
+                    _ => name.push_str(&format!("%{b:02X}")),
+                }
+            }
+            format!("{}/{name}.json", self.root)
+        }
+        fn load(&self, namespace: &str) -> Result<BTreeMap<String, Json>, SystemError> {
+            extern crate std as real_std;
+            let path = self.path(namespace);
+            match real_std::fs::read_to_string(&path) {
+                Ok(content) => serde_json::from_str(&content).map_err(|e| SystemError::Other { description: format!("failed to parse '{path}': {e}") }),
+                Err(e) if e.kind() == real_std::io::ErrorKind::NotFound => Ok(Default::default()),
+                Err(e) => Err(SystemError::Other { description: format!("failed to read '{path}': {e}") }),
+            }
+        }
+        fn store(&self, namespace: &str, entries: &BTreeMap<String, Json>) -> Result<(), SystemError> {
+            extern crate std as real_std;
+            let path = self.path(namespace);
+            let tmp_path = format!("{path}.tmp");
+            let io_error = |e: real_std::io::Error| SystemError::Other { description: format!("failed to write '{path}': {e}") };
+            real_std::fs::create_dir_all(&self.root).map_err(io_error)?;
+            real_std::fs::write(&tmp_path, Json::from_iter(entries.clone()).to_string()).map_err(io_error)?;
+            real_std::fs::rename(&tmp_path, &path).map_err(io_error)
+        }
+    }
+    #[cfg(any(test, feature = "std"))]
+    impl Storage for FileStorage {
+        fn get(&self, namespace: &str, key: &str) -> Result<Option<Json>, SystemError> {
+            Ok(self.load(namespace)?.remove(key))
+        }
+        fn set(&self, namespace: &str, key: &str, value: Json) -> Result<(), SystemError> {
+            let mut entries = self.load(namespace)?;
+            entries.insert(key.to_owned(), value);
+            self.store(namespace, &entries)
+        }
+        fn delete(&self, namespace: &str, key: &str) -> Result<bool, SystemError> {
+            let mut entries = self.load(namespace)?;
+            let res = entries.remove(key).is_some();
+            if res { self.store(namespace, &entries)? }
+            Ok(res)
+        }
+        fn list(&self, namespace: &str) -> Result<Vec<String>, SystemError> {
+            Ok(self.load(namespace)?.into_keys().collect())
+        }
+    }
+}
+pub use storage::*;
+
+#[cfg(any(test, feature = "std"))]
+mod files {
+    extern crate std as real_std;
+    use real_std::path::PathBuf;
+    use real_std::fs;
+
+    use super::*;
+
+    /// Provides file access restricted to a single root directory, as used by [`System::read_file`] and related methods.
+    /// This requires the [`std`](crate) feature flag.
+    ///
+    /// Paths use `/` as a separator and are always relative to the root, even if they begin with `/`.
+    /// Paths which would leave the root through `..` are rejected, as are paths which pass through a symbolic link.
+    pub struct FileSandbox {
+        root: PathBuf,
+    }
+    impl FileSandbox {
+        /// Creates a new [`FileSandbox`] for the `root` directory, which must exist when files are accessed.
+        pub fn new(root: &str) -> Self {
+            Self { root: PathBuf::from(root) }
+        }
+        fn error(path: &str, description: &str) -> SystemError {
+            SystemError::Other { description: format!("{description}: '{path}'") }
+        }
+        fn resolve(&self, path: &str) -> Result<PathBuf, SystemError> {
+            let mut parts: Vec<&str> = vec![];
+            for part in path.split(['/', '\\']) {
+                match part {
+                    "" | "." => (),
+                    ".." => if parts.pop().is_none() { return Err(Self::error(path, "path is outside of the sandbox")) },
+                    x if x.contains(':') => return Err(Self::error(path, "invalid path")),
+                    x => parts.push(x),
+                }
+            }
+
+            let root = self.root.canonicalize().map_err(|e| Self::error(&self.root.to_string_lossy(), &format!("failed to open sandbox root ({e})")))?;
+            // symbolic links could point outside the root (even dangling ones, once written through), so reject them entirely
+            let mut res = root;
+            let mut exists = true;
+            for part in parts {
+                res.push(part);
+                if exists {
+                    match fs::symlink_metadata(&res) {
+                        Ok(meta) => if meta.file_type().is_symlink() { return Err(Self::error(path, "symbolic links are not allowed")) },
+                        Err(_) => exists = false,
+                    }
+                }
+            }
+            Ok(res)
+        }
+        /// Reads the entire content of a text file (see [`System::read_file`]).
+        pub fn read_file(&self, path: &str) -> Result<String, SystemError> {
+            fs::read_to_string(self.resolve(path)?).map_err(|e| Self::error(path, &format!("failed to read file ({e})")))
+        }
+        /// Writes a text file (see [`System::write_file`]).
+        pub fn write_file(&self, path: &str, content: &str) -> Result<(), SystemError> {
+            let full_path = self.resolve(path)?;
+            let io_error = |e: real_std::io::Error| Self::error(path, &format!("failed to write file ({e})"));
+            if let Some(parent) = full_path.parent() { fs::create_dir_all(parent).map_err(io_error)? }
+            fs::write(full_path, content).map_err(io_error)
+        }
+        /// Lists the entries of a directory (see [`System::list_dir`]).
+        pub fn list_dir(&self, path: &str) -> Result<Vec<String>, SystemError> {
+            let io_error = |e: real_std::io::Error| Self::error(path, &format!("failed to list directory ({e})"));
+            let mut res = vec![];
+            for entry in fs::read_dir(self.resolve(path)?).map_err(io_error)? {
+                let entry = entry.map_err(io_error)?;
+                let mut name = entry.file_name().to_string_lossy().into_owned();
+                if entry.file_type().map_err(io_error)?.is_dir() { name.push('/') }
+                res.push(name);
+            }
+            res.sort_unstable();
+            Ok(res)
+        }
+    }
 }
+#[cfg(any(test, feature = "std"))]
+pub use files::*;
 
 #[cfg(any(test, feature = "std"))]
 mod std_system {
     extern crate std as real_std;
     use real_std::time::{Instant, SystemTime, UNIX_EPOCH};
     use real_std::sync::{Arc, Mutex};
-    use real_std::sync::mpsc::{Sender, Receiver, channel};
-    use real_std::thread;
+    use real_std::time::Duration;
+
+    use tokio::runtime::{Runtime, Handle};
+    use reqwest::header::{HeaderMap, HeaderName, HeaderValue, CONTENT_TYPE};
+    use derive_builder::Builder;
 
     use super::*;
     use crate::slotmap::SlotMap;
@@ -691,101 +1465,225 @@ mod std_system {
         role_name: String,
         role_id: String,
     }
-    struct RpcRequest {
-        service: String,
-        rpc: String,
-        args: Vec<(String, Json)>,
-        result_key: RpcKey,
+
+    type RpcResults = SlotMap<RpcKey, Option<Result<RpcResponse, String>>>;
+
+    /// Configuration options for creating a [`StdSystem`].
+    #[derive(Builder, Clone)]
+    #[builder(no_std)]
+    pub struct StdSystemConfig {
+        /// The base url of the NetsBlox server to use for RPCs (default `"https://editor.netsblox.org"`).
+        #[builder(default = "\"https://editor.netsblox.org\".to_owned()")]
+        base_url: String,
+        /// The name of the project to create on the server (default `"untitled"`).
+        #[builder(default = "None")]
+        project_name: Option<String>,
+        /// The client id to use, or [`None`] to generate a new random one (default [`None`]).
+        #[builder(default = "None")]
+        client_id: Option<String>,
+        /// The ids of an existing project and role on the server to use, rather than creating a new project (default [`None`]).
+        #[builder(default = "None")]
+        existing_ids: Option<(String, String)>,
+        /// Extra HTTP headers to include in every request to the server (default empty).
+        #[builder(default = "Vec::new()")]
+        headers: Vec<(String, String)>,
+        /// An authentication cookie to include in every request to the server (default [`None`]).
+        #[builder(default = "None")]
+        cookie: Option<String>,
+        /// The timeout for each request to the server, or [`None`] for no timeout (default [`None`]).
+        #[builder(default = "None")]
+        timeout: Option<Duration>,
+        /// If set to `true`, no requests are ever made to the server, and all RPCs fail with [`SystemError::NotSupported`] (default `false`).
+        #[builder(default = "false")]
+        offline: bool,
+        /// Metadata used to check RPC calls, keyed by service name, e.g., from [`ServiceMetadata::load_all`] (default empty).
+        #[builder(default = "BTreeMap::new()")]
+        service_metadata: BTreeMap<String, ServiceMetadata>,
+        /// If set to `true`, metadata for all services is fetched from the server's service description endpoints upon creation.
+        /// Fetched metadata takes precedence over [`StdSystemConfig::service_metadata`]. This is ignored if [`StdSystemConfig::offline`] is set (default `false`).
+        #[builder(default = "false")]
+        fetch_service_metadata: bool,
+        /// A directory in which to persist data for [`System::storage`] (see [`FileStorage`]), or [`None`] to not support storage (default [`None`]).
+        #[builder(default = "None")]
+        storage_dir: Option<String>,
+        /// The root directory for [`System::read_file`] and related methods (see [`FileSandbox`]), or [`None`] to not support file access (default [`None`]).
+        #[builder(default = "None")]
+        file_root: Option<String>,
+    }
+
+    /// An error from creating a [`StdSystem`].
+    #[derive(Debug)]
+    pub enum StdSystemError {
+        /// Failed to set up the tokio runtime or HTTP client, e.g., due to an invalid header in [`StdSystemConfig`].
+        Setup { description: String },
+        /// Failed to complete a request to the server.
+        Request { url: String, description: String },
+        /// The server sent a response that could not be understood.
+        BadResponse { url: String, description: String },
+    }
+
+    async fn post_json(client: &reqwest::Client, url: String, body: Json) -> Result<BTreeMap<String, Json>, StdSystemError> {
+        let res = match client.post(&url).json(&body).send().await {
+            Ok(x) => x,
+            Err(e) => return Err(StdSystemError::Request { url, description: e.to_string() }),
+        };
+        let status = res.status();
+        if !status.is_success() {
+            let description = format!("{status}: {}", res.text().await.unwrap_or_default());
+            return Err(StdSystemError::BadResponse { url, description });
+        }
+        match res.json().await {
+            Ok(x) => Ok(x),
+            Err(e) => Err(StdSystemError::BadResponse { url, description: e.to_string() }),
+        }
     }
+    async fn get_json(client: &reqwest::Client, url: String) -> Result<Json, StdSystemError> {
+        let res = match client.get(&url).send().await {
+            Ok(x) => x,
+            Err(e) => return Err(StdSystemError::Request { url, description: e.to_string() }),
+        };
+        let status = res.status();
+        if !status.is_success() {
+            let description = format!("{status}: {}", res.text().await.unwrap_or_default());
+            return Err(StdSystemError::BadResponse { url, description });
+        }
+        match res.json().await {
+            Ok(x) => Ok(x),
+            Err(e) => Err(StdSystemError::BadResponse { url, description: e.to_string() }),
+        }
+    }
+    async fn fetch_service_metadata(client: &reqwest::Client, base_url: &str) -> Result<BTreeMap<String, ServiceMetadata>, StdSystemError> {
+        let url = format!("{base_url}/services");
+        let services = get_json(client, url.clone()).await?;
+        let names = services.as_array().ok_or_else(|| StdSystemError::BadResponse { url: url.clone(), description: "expected an array of services".into() })?
+            .iter().map(|x| x.as_str().or_else(|| x.get("name").and_then(Json::as_str)).map(str::to_owned))
+            .collect::<Option<Vec<_>>>().ok_or_else(|| StdSystemError::BadResponse { url, description: "expected service names".into() })?;
 
-    type RpcResults = SlotMap<RpcKey, Option<Result<Json, String>>>;
+        let tasks: Vec<_> = names.into_iter().map(|name| {
+            let (client, url) = (client.clone(), format!("{base_url}/services/{name}"));
+            tokio::spawn(async move {
+                let desc = get_json(&client, url.clone()).await?;
+                match ServiceMetadata::from_json(&desc) {
+                    Ok(meta) => Ok((name, meta)),
+                    Err(e) => Err(StdSystemError::BadResponse { url, description: format!("{e:?}") }),
+                }
+            })
+        }).collect();
+
+        let mut res = BTreeMap::new();
+        for task in tasks {
+            let (name, meta) = task.await.map_err(|e| StdSystemError::Setup { description: e.to_string() })??;
+            res.insert(name, meta);
+        }
+        Ok(res)
+    }
+    /// Checks if an RPC response with the given content type should be interpreted as text (possibly JSON) rather than binary data.
+    fn is_text_content(content_type: &str) -> bool {
+        if content_type.starts_with("image/") { return false }
+        content_type.is_empty() || content_type.starts_with("text/") || content_type.contains("json") || content_type.contains("xml")
+    }
+    fn get_str(meta: &BTreeMap<String, Json>, url: &str, field: &str) -> Result<String, StdSystemError> {
+        match meta.get(field).and_then(Json::as_str) {
+            Some(x) => Ok(x.to_owned()),
+            None => Err(StdSystemError::BadResponse { url: url.to_owned(), description: format!("missing field '{field}'") }),
+        }
+    }
 
     /// A type implementing the [`System`] trait which supports all features.
     /// This requires the [`std`](crate) feature flag.
+    ///
+    /// RPC requests are executed as tasks on a tokio runtime.
+    /// A system created with [`StdSystem::new_async`] uses the runtime it was created on, so many systems can share a single runtime,
+    /// whereas [`StdSystem::new`] creates a dedicated runtime for the new system.
+    /// The system is [`Send`], so it can be created on one thread and moved to the thread which runs its project.
     pub struct StdSystem {
         start_time: Instant,
         context: Arc<Context>,
+        client: reqwest::Client,
+        runtime: Handle,
+        _owned_runtime: Option<Runtime>,
+        offline: bool,
+        service_metadata: BTreeMap<String, Arc<ServiceMetadata>>,
+        storage: Option<FileStorage>,
+        files: Option<FileSandbox>,
 
         rpc_results: Arc<Mutex<RpcResults>>,
-        rpc_request_pipe: Sender<RpcRequest>,
+        waker: Arc<Mutex<Option<Waker>>>,
     }
     impl StdSystem {
-        #[tokio::main(flavor = "current_thread")]
-        pub async fn new(base_url: String, project_name: Option<&str>) -> Self {
+        /// Creates a new [`StdSystem`] with its own dedicated tokio runtime.
+        /// This must not be called (or dropped) from within an async context; use [`StdSystem::new_async`] instead.
+        pub fn new(config: StdSystemConfig) -> Result<Self, StdSystemError> {
+            let runtime = match tokio::runtime::Builder::new_multi_thread().worker_threads(1).enable_all().build() {
+                Ok(x) => x,
+                Err(e) => return Err(StdSystemError::Setup { description: e.to_string() }),
+            };
+            let mut res = runtime.block_on(Self::new_async(config))?;
+            res._owned_runtime = Some(runtime);
+            Ok(res)
+        }
+        /// Creates a new [`StdSystem`] which executes requests on the current tokio runtime.
+        /// This must be called from within a tokio runtime.
+        pub async fn new_async(config: StdSystemConfig) -> Result<Self, StdSystemError> {
+            let mut headers = HeaderMap::new();
+            for (name, value) in config.headers.iter().map(|(a, b)| (a.as_str(), b.as_str())).chain(config.cookie.as_deref().map(|x| ("Cookie", x))) {
+                let name = HeaderName::from_bytes(name.as_bytes()).map_err(|e| StdSystemError::Setup { description: format!("invalid header name '{name}': {e}") })?;
+                let value = HeaderValue::from_str(value).map_err(|e| StdSystemError::Setup { description: format!("invalid header value for '{name}': {e}") })?;
+                headers.append(name, value);
+            }
+
+            let mut client = reqwest::Client::builder().default_headers(headers);
+            if let Some(timeout) = config.timeout { client = client.timeout(timeout) }
+            let client = client.build().map_err(|e| StdSystemError::Setup { description: e.to_string() })?;
+
             let mut context = Context {
-                base_url,
-                client_id: format!("vm-{}", names::Generator::default().next().unwrap()),
+                base_url: config.base_url,
+                client_id: config.client_id.unwrap_or_else(|| format!("vm-{}", names::Generator::default().next().unwrap())),
 
-                project_name: String::new(),
+                project_name: config.project_name.unwrap_or_else(|| "untitled".to_owned()),
                 project_id: String::new(),
                 role_name: String::new(),
                 role_id: String::new(),
             };
 
-            let client = reqwest::Client::builder().build().unwrap();
-            let meta = client.post(format!("{}/api/newProject", context.base_url))
-                .json(&json!({ "clientId": context.client_id, "roleName": "monad" }))
-                .send().await.unwrap()
-                .json::<BTreeMap<String, Json>>().await.unwrap();
-            context.project_id = meta["projectId"].as_str().unwrap().to_owned();
-            context.role_id = meta["roleId"].as_str().unwrap().to_owned();
-            context.role_name = meta["roleName"].as_str().unwrap().to_owned();
-
-            let meta = client.post(format!("{}/api/setProjectName", context.base_url))
-                .json(&json!({ "projectId": context.project_id, "name": project_name.unwrap_or("untitled") }))
-                .send().await.unwrap()
-                .json::<BTreeMap<String, Json>>().await.unwrap();
-            context.project_name = meta["name"].as_str().unwrap().to_owned();
-
-            let context = Arc::new(context);
-            let rpc_results = Arc::new(Mutex::new(Default::default()));
-
-            let rpc_request_pipe = {
-                let context = context.clone();
-                let rpc_results = rpc_results.clone();
-                let (sender, receiver) = channel();
-
-                #[tokio::main(flavor = "multi_thread", worker_threads = 1)]
-                async fn handler(client: reqwest::Client, context: Arc<Context>, rpc_results: Arc<Mutex<RpcResults>>, receiver: Receiver<RpcRequest>) {
-                    while let Ok(request) = receiver.recv() {
-                        let time = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_millis();
-                        let url = format!("{base_url}/services/{service}/{rpc}?uuid={client_id}&projectId={project_id}&roleId={role_id}&t={time}",
-                            service = request.service, rpc = request.rpc,
-                            base_url = context.base_url, client_id = context.client_id, project_id = context.project_id, role_id = context.role_id);
-                        let args: BTreeMap<String, Json> = request.args.into_iter().collect();
-
-                        let req = client.post(url).json(&args);
-                        let context = context.clone();
-                        let rpc_results = rpc_results.clone();
-                        let result_key = request.result_key;
-                        tokio::spawn(async move {
-                            let res = match req.send().await {
-                                Ok(res) => {
-                                    let status = res.status();
-                                    match res.text().await {
-                                        Ok(text) => match status.is_success() {
-                                            true => Ok(serde_json::from_str(&text).unwrap_or(Json::String(text))),
-                                            false => Err(text),
-                                        }
-                                        Err(_) => Err("Failed to read response body".to_owned()),
-                                    }
-                                }
-                                Err(_) => Err(format!("Failed to reach {}", context.base_url)),
-                            };
-                            assert!(rpc_results.lock().unwrap().get_mut(result_key).unwrap().replace(res).is_none());
-                        });
-                    }
+            match config.existing_ids {
+                Some((project_id, role_id)) => {
+                    context.project_id = project_id;
+                    context.role_id = role_id;
                 }
-                thread::spawn(move || handler(client, context, rpc_results, receiver));
+                None if config.offline => (),
+                None => {
+                    let url = format!("{}/api/newProject", context.base_url);
+                    let meta = post_json(&client, url.clone(), json!({ "clientId": context.client_id, "roleName": "monad" })).await?;
+                    context.project_id = get_str(&meta, &url, "projectId")?;
+                    context.role_id = get_str(&meta, &url, "roleId")?;
+                    context.role_name = get_str(&meta, &url, "roleName")?;
 
-                sender
-            };
+                    let url = format!("{}/api/setProjectName", context.base_url);
+                    let meta = post_json(&client, url.clone(), json!({ "projectId": context.project_id, "name": context.project_name })).await?;
+                    context.project_name = get_str(&meta, &url, "name")?;
+                }
+            }
 
-            Self {
-                start_time: Instant::now(),
-                context,
-                rpc_results, rpc_request_pipe,
+            let mut service_metadata = config.service_metadata;
+            if config.fetch_service_metadata && !config.offline {
+                service_metadata.extend(fetch_service_metadata(&client, &context.base_url).await?);
             }
+
+            Ok(Self {
+                start_time: Instant::now(),
+                context: Arc::new(context),
+                client,
+                runtime: Handle::current(),
+                _owned_runtime: None,
+                offline: config.offline,
+                service_metadata: service_metadata.into_iter().map(|(k, v)| (k, Arc::new(v))).collect(),
+                storage: config.storage_dir.as_deref().map(FileStorage::new),
+                files: config.file_root.as_deref().map(FileSandbox::new),
+
+                rpc_results: Arc::new(Mutex::new(Default::default())),
+                waker: Arc::new(Mutex::new(None)),
+            })
         }
     }
     impl System for StdSystem {
@@ -794,20 +1692,749 @@ mod std_system {
         fn time_ms(&self) -> Result<u64, SystemError> {
             Ok(self.start_time.elapsed().as_millis() as u64)
         }
+        fn random(&self) -> Result<f64, SystemError> {
+            Ok(rand::random())
+        }
 
         fn call_rpc(&self, service: String, rpc: String, args: Vec<(String, Json)>) -> Result<Self::RpcKey, SystemError> {
+            if self.offline { return Err(SystemError::NotSupported { feature: SystemFeature::Rpc }) }
+
             let result_key = self.rpc_results.lock().unwrap().insert(None);
-            self.rpc_request_pipe.send(RpcRequest { service, rpc, args, result_key }).unwrap();
+
+            let time = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_millis();
+            let context = self.context.clone();
+            let url = format!("{base_url}/services/{service}/{rpc}?uuid={client_id}&projectId={project_id}&roleId={role_id}&t={time}",
+                base_url = context.base_url, client_id = context.client_id, project_id = context.project_id, role_id = context.role_id);
+            let args: BTreeMap<String, Json> = args.into_iter().collect();
+
+            let req = self.client.post(url).json(&args);
+            let rpc_results = self.rpc_results.clone();
+            let waker = self.waker.clone();
+            self.runtime.spawn(async move {
+                let res = match req.send().await {
+                    Ok(res) => {
+                        let status = res.status();
+                        let content_type = res.headers().get(CONTENT_TYPE).and_then(|x| x.to_str().ok()).unwrap_or("").split(';').next().unwrap().trim().to_owned();
+                        match status.is_success() && !is_text_content(&content_type) {
+                            true => match res.bytes().await {
+                                Ok(data) => Ok(Blob { content_type, data: data.to_vec() }.into()),
+                                Err(_) => Err("Failed to read response body".to_owned()),
+                            }
+                            false => match res.text().await {
+                                Ok(text) => match status.is_success() {
+                                    true => Ok(serde_json::from_str(&text).unwrap_or(Json::String(text)).into()),
+                                    false => Err(text),
+                                }
+                                Err(_) => Err("Failed to read response body".to_owned()),
+                            }
+                        }
+                    }
+                    Err(_) => Err(format!("Failed to reach {}", context.base_url)),
+                };
+                if let Some(slot) = rpc_results.lock().unwrap().get_mut(result_key) {
+                    assert!(slot.replace(res).is_none());
+                } else {
+                    return; // the request was cancelled
+                }
+                if let Some(waker) = waker.lock().unwrap().take() { waker.wake() }
+            });
+
             Ok(result_key)
         }
-        fn poll_rpc(&self, key: &Self::RpcKey) -> Result<AsyncPoll<Result<Json, String>>, SystemError> {
+        fn poll_rpc(&self, key: &Self::RpcKey) -> Result<AsyncPoll<Result<RpcResponse, String>>, SystemError> {
             let mut rpc_results = self.rpc_results.lock().unwrap();
             Ok(match rpc_results.get(*key).unwrap().is_some() {
                 true => AsyncPoll::Completed(rpc_results.remove(*key).unwrap().unwrap()),
                 false => AsyncPoll::Pending,
             })
         }
+        fn cancel_rpc(&self, key: &Self::RpcKey) {
+            self.rpc_results.lock().unwrap().remove(*key);
+        }
+
+        fn register_waker(&self, waker: &Waker) {
+            let mut stored = self.waker.lock().unwrap();
+            match self.rpc_results.lock().unwrap().iter().any(|(_, x)| x.is_some()) {
+                true => waker.wake_by_ref(),
+                false => *stored = Some(waker.clone()),
+            }
+        }
+
+        fn service_metadata(&self, service: &str) -> Option<Arc<ServiceMetadata>> {
+            self.service_metadata.get(service).cloned()
+        }
+
+        fn storage(&self) -> Result<&dyn Storage, SystemError> {
+            match &self.storage {
+                Some(x) => Ok(x),
+                None => Err(SystemError::NotSupported { feature: SystemFeature::Storage }),
+            }
+        }
+
+        fn read_file(&self, path: &str) -> Result<String, SystemError> {
+            self.files.as_ref().ok_or(SystemError::NotSupported { feature: SystemFeature::Files })?.read_file(path)
+        }
+        fn write_file(&self, path: &str, content: &str) -> Result<(), SystemError> {
+            self.files.as_ref().ok_or(SystemError::NotSupported { feature: SystemFeature::Files })?.write_file(path, content)
+        }
+        fn list_dir(&self, path: &str) -> Result<Vec<String>, SystemError> {
+            self.files.as_ref().ok_or(SystemError::NotSupported { feature: SystemFeature::Files })?.list_dir(path)
+        }
     }
 }
 #[cfg(any(test, feature = "std"))]
 pub use std_system::*;
+
+mod mock_system {
+    use std::cell::{Cell, RefCell};
+    use std::iter;
+
+    use super::*;
+    use crate::slotmap::SlotMap;
+
+    new_key! {
+        pub struct MockRpcKey;
+    }
+
+    /// A record of an RPC request made to a [`MockSystem`] (see [`MockSystem::call_log`]).
+    #[derive(Debug, Clone, PartialEq)]
+    pub struct MockRpcCall {
+        pub service: String,
+        pub rpc: String,
+        pub args: Vec<(String, Json)>,
+        /// The (virtual) time at which the request was made.
+        pub time: u64,
+    }
+
+    type PendingResults = SlotMap<MockRpcKey, (u64, Result<RpcResponse, String>)>;
+
+    struct MockResponse {
+        service: String,
+        rpc: String,
+        args: Option<Vec<(String, Json)>>,
+        latency: u64,
+        result: Result<RpcResponse, String>,
+    }
+
+    /// Compares two [`Json`] values, treating numbers as equal if they have the same [`f64`] value.
+    fn json_eq(a: &Json, b: &Json) -> bool {
+        match (a, b) {
+            (Json::Number(a), Json::Number(b)) => a.as_f64() == b.as_f64(),
+            (Json::Array(a), Json::Array(b)) => a.len() == b.len() && iter::zip(a, b).all(|(a, b)| json_eq(a, b)),
+            (Json::Object(a), Json::Object(b)) => a.len() == b.len() && a.iter().all(|(k, v)| b.get(k).map(|x| json_eq(v, x)).unwrap_or(false)),
+            (a, b) => a == b,
+        }
+    }
+
+    /// A type implementing the [`System`] trait which is fully deterministic, for use in testing.
+    ///
+    /// Time is measured by a virtual clock, which starts at zero and only moves when explicitly advanced (see [`MockSystem::advance_to`]).
+    /// RPC results are scripted ahead of time (see [`MockSystem::add_response`]), and become available once the clock reaches
+    /// the time of the request plus the configured latency. Every RPC request is recorded (see [`MockSystem::call_log`]).
+    /// Storage is kept in memory (see [`MemoryStorage`]), and random numbers come from a fixed-seed generator, so every run is reproducible.
+    #[derive(Default)]
+    pub struct MockSystem {
+        time: Cell<u64>,
+        rng_state: Cell<u64>,
+        responses: RefCell<Vec<MockResponse>>,
+        pending: RefCell<PendingResults>,
+        log: RefCell<Vec<MockRpcCall>>,
+        waker: RefCell<Option<Waker>>,
+        metadata: RefCell<BTreeMap<String, Arc<ServiceMetadata>>>,
+        storage: MemoryStorage,
+    }
+    impl MockSystem {
+        /// Creates a new [`MockSystem`] with the clock at time zero and no scripted responses.
+        pub fn new() -> Self {
+            Default::default()
+        }
+        /// Registers the result of calling the given RPC, which becomes available `latency` milliseconds (of virtual time) after the request.
+        /// If `args` is [`None`], this response is used regardless of the arguments; otherwise the arguments must match exactly (in any order).
+        /// When several registered responses match a request, the most recently registered one is used.
+        ///
+        /// Requests for which no response was registered fail with [`SystemError::Other`].
+        pub fn add_response(&self, service: &str, rpc: &str, args: Option<&[(&str, Json)]>, latency: u64, result: Result<Json, String>) {
+            self.push_response(service, rpc, args, latency, result.map(Into::into));
+        }
+        /// Equivalent to [`MockSystem::add_response`], but registers a successful binary result, such as an image.
+        pub fn add_blob_response(&self, service: &str, rpc: &str, args: Option<&[(&str, Json)]>, latency: u64, result: Blob) {
+            self.push_response(service, rpc, args, latency, Ok(result.into()));
+        }
+        fn push_response(&self, service: &str, rpc: &str, args: Option<&[(&str, Json)]>, latency: u64, result: Result<RpcResponse, String>) {
+            self.responses.borrow_mut().push(MockResponse {
+                service: service.to_owned(),
+                rpc: rpc.to_owned(),
+                args: args.map(|x| x.iter().map(|(k, v)| ((*k).to_owned(), v.clone())).collect()),
+                latency, result,
+            });
+        }
+        /// Sets the metadata for the given service, which will be returned by [`System::service_metadata`].
+        pub fn set_service_metadata(&self, service: &str, metadata: ServiceMetadata) {
+            self.metadata.borrow_mut().insert(service.to_owned(), Arc::new(metadata));
+        }
+        /// Gets a copy of the log of all RPC requests made so far, in order.
+        pub fn call_log(&self) -> Vec<MockRpcCall> {
+            self.log.borrow().clone()
+        }
+        /// Clears the log of RPC requests (see [`MockSystem::call_log`]).
+        pub fn clear_call_log(&self) {
+            self.log.borrow_mut().clear();
+        }
+        /// Advances the virtual clock to `time`. If `time` is in the past, this does nothing.
+        pub fn advance_to(&self, time: u64) {
+            if time <= self.time.get() { return }
+            self.time.set(time);
+            if self.pending.borrow().iter().any(|(_, x)| x.0 <= time) {
+                if let Some(waker) = self.waker.borrow_mut().take() { waker.wake() }
+            }
+        }
+        /// Advances the virtual clock by `ms` milliseconds.
+        pub fn advance_by(&self, ms: u64) {
+            self.advance_to(self.time.get().saturating_add(ms));
+        }
+        /// Gets the number of RPC requests which have been made but have not yet been polled to completion or cancelled.
+        pub fn pending_rpcs(&self) -> usize {
+            self.pending.borrow().len()
+        }
+        /// Gets the earliest time at which a pending RPC result becomes available, if any.
+        pub fn next_event_time(&self) -> Option<u64> {
+            self.pending.borrow().iter().map(|(_, x)| x.0).min()
+        }
+    }
+    impl System for MockSystem {
+        type RpcKey = MockRpcKey;
+
+        fn time_ms(&self) -> Result<u64, SystemError> {
+            Ok(self.time.get())
+        }
+        fn random(&self) -> Result<f64, SystemError> {
+            // splitmix64, which is plenty for reproducible tests
+            let state = self.rng_state.get().wrapping_add(0x9e3779b97f4a7c15);
+            self.rng_state.set(state);
+            let mut x = state;
+            x = (x ^ (x >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+            x = (x ^ (x >> 27)).wrapping_mul(0x94d049bb133111eb);
+            x ^= x >> 31;
+            Ok((x >> 11) as f64 / (1u64 << 53) as f64)
+        }
+
+        fn call_rpc(&self, service: String, rpc: String, args: Vec<(String, Json)>) -> Result<Self::RpcKey, SystemError> {
+            let time = self.time.get();
+            let response = {
+                let responses = self.responses.borrow();
+                responses.iter().rev().find(|x| {
+                    x.service == service && x.rpc == rpc && x.args.as_ref().map(|expected| {
+                        expected.len() == args.len() && expected.iter().all(|(k, v)| args.iter().any(|(k2, v2)| k == k2 && json_eq(v, v2)))
+                    }).unwrap_or(true)
+                }).map(|x| (time.saturating_add(x.latency), x.result.clone()))
+            };
+            let res = match response {
+                Some(response) => Ok(self.pending.borrow_mut().insert(response)),
+                None => Err(SystemError::Other { description: format!("no mock response for {service}.{rpc} with args {args:?}") }),
+            };
+            self.log.borrow_mut().push(MockRpcCall { service, rpc, args, time });
+            res
+        }
+        fn poll_rpc(&self, key: &Self::RpcKey) -> Result<AsyncPoll<Result<RpcResponse, String>>, SystemError> {
+            let mut pending = self.pending.borrow_mut();
+            Ok(match pending.get(*key).unwrap().0 <= self.time.get() {
+                true => AsyncPoll::Completed(pending.remove(*key).unwrap().1),
+                false => AsyncPoll::Pending,
+            })
+        }
+        fn cancel_rpc(&self, key: &Self::RpcKey) {
+            self.pending.borrow_mut().remove(*key);
+        }
+
+        fn register_waker(&self, waker: &Waker) {
+            let time = self.time.get();
+            match self.pending.borrow().iter().any(|(_, x)| x.0 <= time) {
+                true => waker.wake_by_ref(),
+                false => *self.waker.borrow_mut() = Some(waker.clone()),
+            }
+        }
+
+        fn service_metadata(&self, service: &str) -> Option<Arc<ServiceMetadata>> {
+            self.metadata.borrow().get(service).cloned()
+        }
+
+        fn storage(&self) -> Result<&dyn Storage, SystemError> {
+            Ok(&self.storage)
+        }
+    }
+}
+pub use mock_system::*;
+
+mod replay_system {
+    use std::cell::{Cell, RefCell};
+
+    use super::*;
+
+    /// An [`RpcKey`](System::RpcKey) used by [`RecordSystem`].
+    #[derive(Collect)]
+    #[collect(require_static)]
+    pub struct RecordKey<K: 'static> {
+        inner: K,
+        index: usize,
+    }
+
+    #[derive(Clone)]
+    struct RecordEntry {
+        service: String,
+        rpc: String,
+        args: Vec<(String, Json)>,
+        result: Option<Result<RpcResponse, String>>,
+    }
+    impl RecordEntry {
+        fn to_json(&self) -> Json {
+            json!({
+                "service": self.service,
+                "rpc": self.rpc,
+                "args": self.args.iter().map(|(k, v)| json!([k, v])).collect::<Vec<_>>(),
+                "result": match &self.result {
+                    None => Json::Null,
+                    Some(Ok(RpcResponse::Json(x))) => json!({ "ok": x }),
+                    Some(Ok(RpcResponse::Blob(x))) => json!({ "blob": x.to_data_url() }),
+                    Some(Err(x)) => json!({ "err": x }),
+                },
+            })
+        }
+        fn from_json(value: &Json) -> Option<Self> {
+            let args = value.get("args")?.as_array()?.iter().map(|x| match x.as_array()?.as_slice() {
+                [k, v] => Some((k.as_str()?.to_owned(), v.clone())),
+                _ => None,
+            }).collect::<Option<Vec<_>>>()?;
+            let result = match value.get("result")? {
+                Json::Null => None,
+                x => match (x.get("ok"), x.get("blob"), x.get("err")) {
+                    (Some(x), None, None) => Some(Ok(x.clone().into())),
+                    (None, Some(x), None) => Some(Ok(Blob::from_data_url(x.as_str()?)?.into())),
+                    (None, None, Some(x)) => Some(Err(x.as_str()?.to_owned())),
+                    _ => return None,
+                }
+            };
+            Some(Self { service: value.get("service")?.as_str()?.to_owned(), rpc: value.get("rpc")?.as_str()?.to_owned(), args, result })
+        }
+    }
+
+    /// An error from saving or loading a recording (see [`RecordSystem`] and [`ReplaySystem`]).
+    #[derive(Debug)]
+    pub enum RecordingError {
+        /// The recording was not valid JSON or did not have the expected structure.
+        BadFormat,
+        /// Failed to read or write the recording file.
+        Io { description: String },
+    }
+
+    /// A [`System`] wrapper which records all RPC requests and their results, which can later be replayed by a [`ReplaySystem`].
+    ///
+    /// All requests are forwarded to the wrapped system, and results are recorded as they are polled.
+    /// Random numbers drawn through [`System::random`] are recorded as well, so that replays are deterministic.
+    pub struct RecordSystem<S: System> {
+        inner: S,
+        entries: RefCell<Vec<RecordEntry>>,
+        randoms: RefCell<Vec<f64>>,
+    }
+    impl<S: System> RecordSystem<S> {
+        /// Wraps a [`System`] to record its RPC traffic.
+        pub fn new(inner: S) -> Self {
+            Self { inner, entries: Default::default(), randoms: Default::default() }
+        }
+        /// Gets a reference to the wrapped system.
+        pub fn inner(&self) -> &S {
+            &self.inner
+        }
+        /// Gets the recorded RPC traffic and random numbers as JSON, in the format expected by [`ReplaySystem::from_json`].
+        /// Requests which have not yet completed are recorded without a result, and will never complete when replayed.
+        pub fn to_json(&self) -> Json {
+            json!({
+                "rpcs": self.entries.borrow().iter().map(RecordEntry::to_json).collect::<Vec<_>>(),
+                "random": *self.randoms.borrow(),
+            })
+        }
+        /// Saves the recording to a JSON file (see [`RecordSystem::to_json`]).
+        /// This requires the [`std`](crate) feature flag.
+        #[cfg(any(test, feature = "std"))]
+        pub fn save(&self, path: &str) -> Result<(), RecordingError> {
+            extern crate std as real_std;
+            real_std::fs::write(path, self.to_json().to_string()).map_err(|e| RecordingError::Io { description: e.to_string() })
+        }
+    }
+    impl<S: System> System for RecordSystem<S> {
+        type RpcKey = RecordKey<S::RpcKey>;
+
+        fn time_ms(&self) -> Result<u64, SystemError> {
+            self.inner.time_ms()
+        }
+        fn random(&self) -> Result<f64, SystemError> {
+            let res = self.inner.random()?;
+            self.randoms.borrow_mut().push(res);
+            Ok(res)
+        }
+
+        fn call_rpc(&self, service: String, rpc: String, args: Vec<(String, Json)>) -> Result<Self::RpcKey, SystemError> {
+            let entry = RecordEntry { service: service.clone(), rpc: rpc.clone(), args: args.clone(), result: None };
+            let inner = self.inner.call_rpc(service, rpc, args)?;
+            let mut entries = self.entries.borrow_mut();
+            entries.push(entry);
+            Ok(RecordKey { inner, index: entries.len() - 1 })
+        }
+        fn poll_rpc(&self, key: &Self::RpcKey) -> Result<AsyncPoll<Result<RpcResponse, String>>, SystemError> {
+            let res = self.inner.poll_rpc(&key.inner)?;
+            if let AsyncPoll::Completed(x) = &res {
+                self.entries.borrow_mut()[key.index].result = Some(x.clone());
+            }
+            Ok(res)
+        }
+        fn cancel_rpc(&self, key: &Self::RpcKey) {
+            self.inner.cancel_rpc(&key.inner)
+        }
+
+        fn register_waker(&self, waker: &Waker) {
+            self.inner.register_waker(waker)
+        }
+
+        fn service_metadata(&self, service: &str) -> Option<Arc<ServiceMetadata>> {
+            self.inner.service_metadata(service)
+        }
+
+        fn storage(&self) -> Result<&dyn Storage, SystemError> {
+            self.inner.storage()
+        }
+
+        fn read_file(&self, path: &str) -> Result<String, SystemError> {
+            self.inner.read_file(path)
+        }
+        fn write_file(&self, path: &str, content: &str) -> Result<(), SystemError> {
+            self.inner.write_file(path, content)
+        }
+        fn list_dir(&self, path: &str) -> Result<Vec<String>, SystemError> {
+            self.inner.list_dir(path)
+        }
+    }
+
+    /// A [`System`] wrapper which serves RPC results from a recording made by [`RecordSystem`], without making any real requests.
+    ///
+    /// Requests must be made in the same order as in the recording, with the same services, RPCs, and arguments;
+    /// otherwise the request fails with [`SystemError::Other`]. Recorded results are available immediately.
+    /// Random numbers are served from the recording in order, and drawing more than were recorded fails with [`SystemError::Other`].
+    /// All other features are forwarded to the wrapped system.
+    pub struct ReplaySystem<S: System> {
+        inner: S,
+        entries: Vec<RecordEntry>,
+        next: Cell<usize>,
+        ready: RefCell<BTreeSet<usize>>,
+        randoms: Vec<f64>,
+        next_random: Cell<usize>,
+    }
+    impl<S: System> ReplaySystem<S> {
+        /// Creates a new [`ReplaySystem`] from a recording produced by [`RecordSystem::to_json`].
+        pub fn from_json(recording: &Json, inner: S) -> Result<Self, RecordingError> {
+            let entries = recording.get("rpcs").and_then(Json::as_array).ok_or(RecordingError::BadFormat)?;
+            let entries = entries.iter().map(RecordEntry::from_json).collect::<Option<Vec<_>>>().ok_or(RecordingError::BadFormat)?;
+            let randoms = match recording.get("random") {
+                None => vec![],
+                Some(x) => x.as_array().and_then(|x| x.iter().map(Json::as_f64).collect::<Option<Vec<_>>>()).ok_or(RecordingError::BadFormat)?,
+            };
+            Ok(Self { inner, entries, next: Cell::new(0), ready: Default::default(), randoms, next_random: Cell::new(0) })
+        }
+        /// Loads a recording from a JSON file (see [`ReplaySystem::from_json`]).
+        /// This requires the [`std`](crate) feature flag.
+        #[cfg(any(test, feature = "std"))]
+        pub fn load(path: &str, inner: S) -> Result<Self, RecordingError> {
+            extern crate std as real_std;
+            let content = real_std::fs::read_to_string(path).map_err(|e| RecordingError::Io { description: e.to_string() })?;
+            let recording = serde_json::from_str(&content).map_err(|_| RecordingError::BadFormat)?;
+            Self::from_json(&recording, inner)
+        }
+        /// Gets a reference to the wrapped system.
+        pub fn inner(&self) -> &S {
+            &self.inner
+        }
+        /// Gets the number of recorded requests which have not yet been replayed.
+        pub fn remaining(&self) -> usize {
+            self.entries.len() - self.next.get()
+        }
+    }
+    impl<S: System> System for ReplaySystem<S> {
+        type RpcKey = usize;
+
+        fn time_ms(&self) -> Result<u64, SystemError> {
+            self.inner.time_ms()
+        }
+        fn random(&self) -> Result<f64, SystemError> {
+            let index = self.next_random.get();
+            let res = *self.randoms.get(index).ok_or_else(|| SystemError::Other { description: "replay has no more recorded random numbers".into() })?;
+            self.next_random.set(index + 1);
+            Ok(res)
+        }
+
+        fn call_rpc(&self, service: String, rpc: String, args: Vec<(String, Json)>) -> Result<Self::RpcKey, SystemError> {
+            let index = self.next.get();
+            let entry = match self.entries.get(index) {
+                Some(x) => x,
+                None => return Err(SystemError::Other { description: format!("replay has no more recorded requests, but got {service}.{rpc}") }),
+            };
+            if entry.service != service || entry.rpc != rpc || entry.args != args {
+                return Err(SystemError::Other { description: format!("replay expected {}.{} with args {:?}, but got {service}.{rpc} with args {args:?}", entry.service, entry.rpc, entry.args) });
+            }
+            self.next.set(index + 1);
+            if entry.result.is_some() { self.ready.borrow_mut().insert(index); }
+            Ok(index)
+        }
+        fn poll_rpc(&self, key: &Self::RpcKey) -> Result<AsyncPoll<Result<RpcResponse, String>>, SystemError> {
+            Ok(match &self.entries[*key].result {
+                Some(x) => {
+                    self.ready.borrow_mut().remove(key);
+                    AsyncPoll::Completed(x.clone())
+                }
+                None => AsyncPoll::Pending,
+            })
+        }
+        fn cancel_rpc(&self, key: &Self::RpcKey) {
+            self.ready.borrow_mut().remove(key);
+        }
+
+        fn register_waker(&self, waker: &Waker) {
+            match self.ready.borrow().is_empty() {
+                true => self.inner.register_waker(waker),
+                false => waker.wake_by_ref(),
+            }
+        }
+
+        fn service_metadata(&self, service: &str) -> Option<Arc<ServiceMetadata>> {
+            self.inner.service_metadata(service)
+        }
+
+        fn storage(&self) -> Result<&dyn Storage, SystemError> {
+            self.inner.storage()
+        }
+
+        fn read_file(&self, path: &str) -> Result<String, SystemError> {
+            self.inner.read_file(path)
+        }
+        fn write_file(&self, path: &str, content: &str) -> Result<(), SystemError> {
+            self.inner.write_file(path, content)
+        }
+        fn list_dir(&self, path: &str) -> Result<Vec<String>, SystemError> {
+            self.inner.list_dir(path)
+        }
+    }
+}
+pub use replay_system::*;
+
+mod routing_system {
+    use std::cell::RefCell;
+
+    use super::*;
+    use crate::slotmap::SlotMap;
+
+    new_key! {
+        struct LocalKey;
+    }
+
+    /// A local implementation of some NetsBlox services (see [`RoutingSystem`]).
+    ///
+    /// This is implemented for any function (or closure) with the same signature as [`ServiceProvider::call`].
+    pub trait ServiceProvider {
+        /// Executes the requested RPC, or returns [`None`] if it is not provided by this provider.
+        /// The result is either the return value (which is always JSON; see [`RpcResponse::Json`]) or an error message, as in [`System::poll_rpc`].
+        fn call(&self, service: &str, rpc: &str, args: &[(String, Json)]) -> Option<Result<Json, String>>;
+    }
+    impl<F: Fn(&str, &str, &[(String, Json)]) -> Option<Result<Json, String>>> ServiceProvider for F {
+        fn call(&self, service: &str, rpc: &str, args: &[(String, Json)]) -> Option<Result<Json, String>> {
+            self(service, rpc, args)
+        }
+    }
+
+    /// Executes a CloudVariables RPC using the given storage.
+    /// Public variables are stored in the `CloudVariables` namespace along with their password, and user variables in `CloudVariables.user`.
+    fn cloud_variables_rpc(storage: &dyn Storage, rpc: &str, args: &[(String, Json)]) -> Result<Result<Json, String>, SystemError> {
+        let arg = |name: &str| args.iter().find(|x| x.0 == name).map(|x| x.1.clone()).unwrap_or_else(|| Json::String(String::new()));
+        let arg_str = |name: &str| match arg(name) {
+            Json::String(x) => x,
+            x => x.to_string(),
+        };
+        let (name, password) = (arg_str("name"), arg_str("password"));
+        let check_password = |entry: &Json| match entry.get("password").and_then(Json::as_str).unwrap_or("") {
+            "" => true,
+            x => x == password,
+        };
+
+        Ok(match rpc {
+            "getVariable" | "deleteVariable" | "setVariable" => {
+                let entry = storage.get("CloudVariables", &name)?;
+                match (rpc, entry) {
+                    (_, Some(entry)) if !check_password(&entry) => Err("Incorrect password".to_owned()),
+                    ("getVariable", Some(entry)) => Ok(entry.get("value").cloned().unwrap_or(Json::Null)),
+                    ("deleteVariable", Some(_)) => {
+                        storage.delete("CloudVariables", &name)?;
+                        Ok(Json::String(String::new()))
+                    }
+                    ("setVariable", _) => {
+                        storage.set("CloudVariables", &name, json!({ "value": arg("value"), "password": password }))?;
+                        Ok(Json::String(String::new()))
+                    }
+                    _ => Err("Variable not found".to_owned()),
+                }
+            }
+            "getUserVariable" => match storage.get("CloudVariables.user", &name)? {
+                Some(x) => Ok(x),
+                None => Err("Variable not found".to_owned()),
+            }
+            "setUserVariable" => {
+                storage.set("CloudVariables.user", &name, arg("value"))?;
+                Ok(Json::String(String::new()))
+            }
+            "deleteUserVariable" => match storage.delete("CloudVariables.user", &name)? {
+                true => Ok(Json::String(String::new())),
+                false => Err("Variable not found".to_owned()),
+            }
+            _ => Err(format!("CloudVariables.{rpc} is not supported locally")),
+        })
+    }
+
+    /// Executes a Files RPC using the file access methods of the given system (see [`System::read_file`]).
+    /// Failures to access a file are reported to the program as RPC errors, but missing support for file access is a system error.
+    fn files_rpc<S: System>(system: &S, rpc: &str, args: &[(String, Json)]) -> Result<Result<Json, String>, SystemError> {
+        let arg = |name: &str| match args.iter().find(|x| x.0 == name).map(|x| &x.1) {
+            Some(Json::String(x)) => x.clone(),
+            Some(x) => x.to_string(),
+            None => String::new(),
+        };
+        let res = match rpc {
+            "readFile" => system.read_file(&arg("path")).map(Json::String),
+            "writeFile" => system.write_file(&arg("path"), &arg("content")).map(|_| Json::String(String::new())),
+            "listDir" => system.list_dir(&arg("path")).map(|x| Json::Array(x.into_iter().map(Json::String).collect())),
+            _ => return Ok(Err(format!("Files.{rpc} is not supported"))),
+        };
+        match res {
+            Ok(x) => Ok(Ok(x)),
+            Err(SystemError::Other { description }) => Ok(Err(description)),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// An [`RpcKey`](System::RpcKey) used by [`RoutingSystem`].
+    #[derive(Collect)]
+    #[collect(require_static)]
+    pub struct RoutedKey<K: 'static>(RoutedKeyInner<K>);
+    #[derive(Collect)]
+    #[collect(require_static)]
+    enum RoutedKeyInner<K: 'static> {
+        Local(LocalKey),
+        Remote(K),
+    }
+
+    /// A [`System`] wrapper which executes RPCs with local [`ServiceProvider`]s where possible,
+    /// and forwards all other requests (and features) to the wrapped system.
+    ///
+    /// Providers are tried in the order they were added, and local results are available immediately.
+    /// The CloudVariables service can also be provided locally using the [`Storage`] of the wrapped system (see [`RoutingSystem::with_cloud_variables`]),
+    /// and a Files service can give programs access to the files of the wrapped system (see [`RoutingSystem::with_files`]).
+    pub struct RoutingSystem<S: System> {
+        inner: S,
+        providers: Vec<Box<dyn ServiceProvider>>,
+        cloud_variables: bool,
+        files: bool,
+        local_results: RefCell<SlotMap<LocalKey, Result<Json, String>>>,
+    }
+    impl<S: System> RoutingSystem<S> {
+        /// Wraps a [`System`] with no local service providers.
+        pub fn new(inner: S) -> Self {
+            Self { inner, providers: vec![], cloud_variables: false, files: false, local_results: Default::default() }
+        }
+        /// Executes CloudVariables RPCs locally using the [`Storage`] of the wrapped system (see [`System::storage`]), so that
+        /// variables persist across runs without a server. This takes priority over the wrapped system, but not over other providers.
+        ///
+        /// Getting, setting, and deleting (password-protected) variables and user variables is supported;
+        /// other CloudVariables RPCs, such as locking and listening to variables, fail with an error message.
+        pub fn with_cloud_variables(mut self) -> Self {
+            self.cloud_variables = true;
+            self
+        }
+        /// Provides a local Files service using the file access methods of the wrapped system (see [`System::read_file`]),
+        /// such as a [`FileSandbox`] directory. This takes priority over the wrapped system, but not over other providers.
+        ///
+        /// The service has the RPCs `readFile(path)`, `writeFile(path, content)`, and `listDir(path)`,
+        /// which programs can call with the dynamic "call RPC" blocks.
+        pub fn with_files(mut self) -> Self {
+            self.files = true;
+            self
+        }
+        /// Adds a local service provider, which takes priority over the wrapped system.
+        pub fn with_provider<P: ServiceProvider + 'static>(mut self, provider: P) -> Self {
+            self.providers.push(Box::new(provider));
+            self
+        }
+        /// Gets a reference to the wrapped system.
+        pub fn inner(&self) -> &S {
+            &self.inner
+        }
+    }
+    impl<S: System> System for RoutingSystem<S> {
+        type RpcKey = RoutedKey<S::RpcKey>;
+
+        fn time_ms(&self) -> Result<u64, SystemError> {
+            self.inner.time_ms()
+        }
+        fn random(&self) -> Result<f64, SystemError> {
+            self.inner.random()
+        }
+
+        fn call_rpc(&self, service: String, rpc: String, args: Vec<(String, Json)>) -> Result<Self::RpcKey, SystemError> {
+            for provider in self.providers.iter() {
+                if let Some(res) = provider.call(&service, &rpc, &args) {
+                    return Ok(RoutedKey(RoutedKeyInner::Local(self.local_results.borrow_mut().insert(res))));
+                }
+            }
+            if self.cloud_variables && service == "CloudVariables" {
+                let res = cloud_variables_rpc(self.inner.storage()?, &rpc, &args)?;
+                return Ok(RoutedKey(RoutedKeyInner::Local(self.local_results.borrow_mut().insert(res))));
+            }
+            if self.files && service == "Files" {
+                let res = files_rpc(&self.inner, &rpc, &args)?;
+                return Ok(RoutedKey(RoutedKeyInner::Local(self.local_results.borrow_mut().insert(res))));
+            }
+            Ok(RoutedKey(RoutedKeyInner::Remote(self.inner.call_rpc(service, rpc, args)?)))
+        }
+        fn poll_rpc(&self, key: &Self::RpcKey) -> Result<AsyncPoll<Result<RpcResponse, String>>, SystemError> {
+            match &key.0 {
+                RoutedKeyInner::Local(key) => Ok(AsyncPoll::Completed(self.local_results.borrow_mut().remove(*key).unwrap().map(Into::into))),
+                RoutedKeyInner::Remote(key) => self.inner.poll_rpc(key),
+            }
+        }
+        fn cancel_rpc(&self, key: &Self::RpcKey) {
+            match &key.0 {
+                RoutedKeyInner::Local(key) => { self.local_results.borrow_mut().remove(*key); }
+                RoutedKeyInner::Remote(key) => self.inner.cancel_rpc(key),
+            }
+        }
+
+        fn register_waker(&self, waker: &Waker) {
+            match self.local_results.borrow().is_empty() {
+                true => self.inner.register_waker(waker),
+                false => waker.wake_by_ref(),
+            }
+        }
+
+        fn service_metadata(&self, service: &str) -> Option<Arc<ServiceMetadata>> {
+            self.inner.service_metadata(service)
+        }
+
+        fn storage(&self) -> Result<&dyn Storage, SystemError> {
+            self.inner.storage()
+        }
+
+        fn read_file(&self, path: &str) -> Result<String, SystemError> {
+            self.inner.read_file(path)
+        }
+        fn write_file(&self, path: &str, content: &str) -> Result<(), SystemError> {
+            self.inner.write_file(path, content)
+        }
+        fn list_dir(&self, path: &str) -> Result<Vec<String>, SystemError> {
+            self.inner.list_dir(path)
+        }
+    }
+}
+pub use routing_system::*;
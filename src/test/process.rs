@@ -2,9 +2,12 @@ use std::prelude::v1::*;
 use std::cell::RefCell;
 use std::rc::Rc;
 use std::fmt::Write;
+use std::collections::BTreeSet;
+use std::iter;
 
 use crate::*;
 use crate::gc::*;
+use crate::json::*;
 use crate::bytecode::*;
 use crate::runtime::*;
 use crate::process::*;
@@ -14,7 +17,7 @@ use super::*;
 #[derive(Collect)]
 #[collect(no_drop)]
 struct Env<'gc> {
-    proc: GcCell<'gc, Process<'gc, StdSystem>>,
+    proc: GcCell<'gc, Process<'gc, MockSystem>>,
     glob: GcCell<'gc, GlobalContext<'gc>>,
 }
 make_arena!(EnvArena, Env);
@@ -38,7 +41,21 @@ fn get_running_proc(xml: &str, settings: Settings) -> EnvArena {
     })
 }
 
-fn run_till_term<F>(env: &mut EnvArena, system: &StdSystem, and_then: F) where F: for<'gc> FnOnce(MutationContext<'gc, '_>, &Env, Result<(Option<Value<'gc>>, usize), ExecError>) {
+fn get_running_ins(ins: Vec<Instruction<'static>>, settings: Settings) -> EnvArena {
+    EnvArena::new(Default::default(), |mc| {
+        let parser = ast::ParserBuilder::default().build().unwrap();
+        let ast = parser.parse(&format!(include_str!("templates/generic-static.xml"), globals = "", fields = "", funcs = "", methods = "")).unwrap();
+
+        let glob = GcCell::allocate(mc, GlobalContext::from_ast(mc, &ast.roles[0]));
+        let mut proc = Process::new(Rc::new(ByteCode::assemble(ins)), 0, glob, glob.read().entities[0], settings);
+        proc.initialize(Default::default(), None);
+        assert!(proc.is_running());
+
+        Env { glob, proc: GcCell::allocate(mc, proc) }
+    })
+}
+
+fn run_till_term<F>(env: &mut EnvArena, system: &MockSystem, and_then: F) where F: for<'gc> FnOnce(MutationContext<'gc, '_>, &Env, Result<(Option<Value<'gc>>, usize), ExecError>) {
     env.mutate(|mc, env| {
         let mut proc = env.proc.write(mc);
         assert!(proc.is_running());
@@ -49,6 +66,11 @@ fn run_till_term<F>(env: &mut EnvArena, system: &StdSystem, and_then: F) where F
                 Ok(ProcessStep::Idle) => panic!(),
                 Ok(ProcessStep::Normal) => (),
                 Ok(ProcessStep::Yield) => yields += 1,
+                Ok(ProcessStep::Waiting { reason }) => match reason {
+                    WaitReason::Timer { wake_time } => system.advance_to(wake_time),
+                    WaitReason::Rpc { deadline } => system.advance_to(deadline.into_iter().chain(system.next_event_time()).min().unwrap()),
+                    WaitReason::Barrier { .. } => panic!("proc tests should not wait on barriers"),
+                }
                 Ok(ProcessStep::Terminate { result }) => break result,
                 Ok(ProcessStep::Broadcast { .. }) => panic!("proc tests should not broadcast"),
                 Err(e) => return and_then(mc, env, Err(e)),
@@ -62,7 +84,7 @@ fn run_till_term<F>(env: &mut EnvArena, system: &StdSystem, and_then: F) where F
 
 #[test]
 fn test_proc_ret() {
-    let system = StdSystem::new("https://editor.netsblox.org".to_owned(), None);
+    let system = MockSystem::new();
     let mut env = get_running_proc(&format!(include_str!("templates/generic-static.xml"),
         globals = "",
         fields = "",
@@ -78,7 +100,7 @@ fn test_proc_ret() {
 
 #[test]
 fn test_proc_sum_123n() {
-    let system = StdSystem::new("https://editor.netsblox.org".to_owned(), None);
+    let system = MockSystem::new();
     let mut env = get_running_proc(&format!(include_str!("templates/generic-static.xml"),
         globals = "",
         fields = "",
@@ -101,7 +123,7 @@ fn test_proc_sum_123n() {
 
 #[test]
 fn test_proc_recursive_factorial() {
-    let system = StdSystem::new("https://editor.netsblox.org".to_owned(), None);
+    let system = MockSystem::new();
     let mut env = get_running_proc(&format!(include_str!("templates/generic-static.xml"),
         globals = "",
         fields = "",
@@ -124,7 +146,7 @@ fn test_proc_recursive_factorial() {
 
 #[test]
 fn test_proc_loops_lists_basic() {
-    let system = StdSystem::new("https://editor.netsblox.org".to_owned(), None);
+    let system = MockSystem::new();
     let mut env = get_running_proc(&format!(include_str!("templates/generic-static.xml"),
         globals = "",
         fields = "",
@@ -164,7 +186,7 @@ fn test_proc_loops_lists_basic() {
 
 #[test]
 fn test_proc_recursively_self_containing_lists() {
-    let system = StdSystem::new("https://editor.netsblox.org".to_owned(), None);
+    let system = MockSystem::new();
     let mut env = get_running_proc(&format!(include_str!("templates/generic-static.xml"),
         globals = "",
         fields = "",
@@ -209,7 +231,7 @@ fn test_proc_recursively_self_containing_lists() {
 
 #[test]
 fn test_proc_sieve_of_eratosthenes() {
-    let system = StdSystem::new("https://editor.netsblox.org".to_owned(), None);
+    let system = MockSystem::new();
     let mut env = get_running_proc(&format!(include_str!("templates/generic-static.xml"),
         globals = "",
         fields = "",
@@ -235,7 +257,7 @@ fn test_proc_sieve_of_eratosthenes() {
 
 #[test]
 fn test_proc_early_return() {
-    let system = StdSystem::new("https://editor.netsblox.org".to_owned(), None);
+    let system = MockSystem::new();
     let mut env = get_running_proc(&format!(include_str!("templates/generic-static.xml"),
         globals = "",
         fields = "",
@@ -251,7 +273,7 @@ fn test_proc_early_return() {
 
 #[test]
 fn test_proc_short_circuit() {
-    let system = StdSystem::new("https://editor.netsblox.org".to_owned(), None);
+    let system = MockSystem::new();
     let mut env = get_running_proc(&format!(include_str!("templates/generic-static.xml"),
         globals = "",
         fields = "",
@@ -279,7 +301,7 @@ fn test_proc_short_circuit() {
 
 #[test]
 fn test_proc_all_arithmetic() {
-    let system = StdSystem::new("https://editor.netsblox.org".to_owned(), None);
+    let system = MockSystem::new();
     let mut env = get_running_proc(&format!(include_str!("templates/generic-static.xml"),
         globals = "",
         fields = "",
@@ -323,7 +345,7 @@ fn test_proc_all_arithmetic() {
 
 #[test]
 fn test_proc_lambda_local_shadow_capture() {
-    let system = StdSystem::new("https://editor.netsblox.org".to_owned(), None);
+    let system = MockSystem::new();
     let mut env = get_running_proc(&format!(include_str!("templates/generic-static.xml"),
         globals = "",
         fields = "",
@@ -339,7 +361,7 @@ fn test_proc_lambda_local_shadow_capture() {
 
 #[test]
 fn test_proc_generators_nested() {
-    let system = StdSystem::new("https://editor.netsblox.org".to_owned(), None);
+    let system = MockSystem::new();
     let mut env = get_running_proc(&format!(include_str!("templates/generic-static.xml"),
         globals = "",
         fields = "",
@@ -355,7 +377,7 @@ fn test_proc_generators_nested() {
 
 #[test]
 fn test_proc_call_in_closure() {
-    let system = StdSystem::new("https://editor.netsblox.org".to_owned(), None);
+    let system = MockSystem::new();
     let mut env = get_running_proc(&format!(include_str!("templates/generic-static.xml"),
         globals = "",
         fields = "",
@@ -374,7 +396,7 @@ fn test_proc_call_in_closure() {
 
 #[test]
 fn test_proc_warp_yields() {
-    let system = StdSystem::new("https://editor.netsblox.org".to_owned(), None);
+    let system = MockSystem::new();
     let mut env = get_running_proc(&format!(include_str!("templates/generic-static.xml"),
         globals = r#"<variable name="counter"><l>0</l></variable>"#,
         fields = "",
@@ -400,7 +422,7 @@ fn test_proc_warp_yields() {
 
 #[test]
 fn test_proc_string_ops() {
-    let system = StdSystem::new("https://editor.netsblox.org".to_owned(), None);
+    let system = MockSystem::new();
     let mut env = get_running_proc(&format!(include_str!("templates/generic-static.xml"),
         globals = "",
         fields = "",
@@ -457,9 +479,471 @@ fn test_proc_string_ops() {
     });
 }
 
+#[test]
+fn test_proc_text_ops() {
+    let system = MockSystem::new();
+    let ins = vec![
+        Instruction::PushNumber { value: 2.0 },
+        Instruction::PushString { value: "héllo" },
+        BinaryOp::StrGet.into(),
+        Instruction::PushNumber { value: 6.0 },
+        Instruction::PushString { value: "héllo" },
+        BinaryOp::StrGet.into(),
+        Instruction::PushNumber { value: 1.0 },
+        Instruction::PushNumber { value: 5.0 },
+        Instruction::MakeList { len: 2 },
+        Instruction::PushString { value: "ab" },
+        Instruction::PushString { value: "cdefg" },
+        Instruction::MakeList { len: 2 },
+        BinaryOp::StrGet.into(),
+
+        Instruction::PushString { value: "hello world" },
+        Instruction::PushString { value: "o w" },
+        BinaryOp::StrContains.into(),
+        Instruction::PushString { value: "hello world" },
+        Instruction::PushString { value: "O W" },
+        BinaryOp::StrContains.into(),
+        Instruction::PushString { value: "wör" },
+        Instruction::PushString { value: "hëllo wörld" },
+        BinaryOp::StrIndexOf.into(),
+        Instruction::PushString { value: "x" },
+        Instruction::PushString { value: "hello" },
+        BinaryOp::StrIndexOf.into(),
+
+        Instruction::PushString { value: "Hello World" },
+        Instruction::PushString { value: "ΣAb" },
+        Instruction::PushNumber { value: 12.0 },
+        Instruction::MakeList { len: 3 },
+        Instruction::DupeValue { top_index: 0 },
+        UnaryOp::StrLower.into(),
+        Instruction::SwapValues { top_index_1: 0, top_index_2: 1 },
+        UnaryOp::StrUpper.into(),
+
+        Instruction::PushString { value: "hello world" },
+        Instruction::PushNumber { value: 2.0 },
+        Instruction::PushNumber { value: 5.0 },
+        Instruction::Substring,
+        Instruction::PushString { value: "abc" },
+        Instruction::PushString { value: "defghi" },
+        Instruction::MakeList { len: 2 },
+        Instruction::PushNumber { value: -3.0 },
+        Instruction::PushNumber { value: 4.0 },
+        Instruction::Substring,
+        Instruction::PushString { value: "hello" },
+        Instruction::PushNumber { value: 4.0 },
+        Instruction::PushNumber { value: 2.0 },
+        Instruction::Substring,
+
+        Instruction::MakeList { len: 12 },
+        Instruction::Return,
+    ];
+    let mut env = get_running_ins(ins, SettingsBuilder::default().build().unwrap());
+    run_till_term(&mut env, &system, |mc, _, res| {
+        let expect = Value::from_simple(mc, simple_value!([
+            "é", "", ["a", "g"],
+            true, false, 7, 0,
+            ["hello world", "σab", "12"], ["HELLO WORLD", "ΣAB", "12"],
+            "ello", ["abc", "defg"], "",
+        ]));
+        assert_values_eq(&res.unwrap().0.unwrap(), &expect, 1e-20, "text ops");
+    });
+}
+
+#[test]
+fn test_proc_list_hof() {
+    fn closure(ins: &mut Vec<Instruction<'static>>, pos: usize, params: &[&'static str]) {
+        ins.extend(params.iter().map(|&value| Instruction::MetaPush { value }));
+        ins.push(Instruction::MakeClosure { pos, params: params.len(), captures: 0, empty_slots: 0 });
+    }
+    fn program(warp: bool) -> Vec<Instruction<'static>> {
+        let mut ins = vec![Instruction::Jump { to: usize::MAX }];
+        let square = ins.len();
+        ins.extend([Instruction::PushVariable { var: "x" }, Instruction::PushVariable { var: "x" }, BinaryOp::Mul.into(), Instruction::Return]);
+        let odd = ins.len();
+        ins.extend([Instruction::PushVariable { var: "x" }, Instruction::PushNumber { value: 2.0 }, BinaryOp::Mod.into(), Instruction::PushNumber { value: 1.0 }, Instruction::Eq, Instruction::Return]);
+        let big = ins.len();
+        ins.extend([Instruction::PushVariable { var: "x" }, Instruction::PushNumber { value: 1.0 }, BinaryOp::Greater.into(), Instruction::Return]);
+        let add = ins.len();
+        ins.extend([Instruction::PushVariable { var: "a" }, Instruction::PushVariable { var: "b" }, BinaryOp::Add.into(), Instruction::Return]);
+        let indexed = ins.len();
+        ins.extend([Instruction::PushVariable { var: "i" }, Instruction::PushNumber { value: 10.0 }, BinaryOp::Mul.into(), Instruction::PushVariable { var: "x" }, BinaryOp::Add.into(), Instruction::Return]);
+        let nested = ins.len();
+        ins.push(Instruction::PushVariable { var: "l" });
+        closure(&mut ins, add, &["a", "b"]);
+        ins.extend([Instruction::ListHof { op: HofOp::Combine }, Instruction::Return]);
+
+        ins[0] = Instruction::Jump { to: ins.len() };
+        if warp { ins.push(Instruction::WarpStart) }
+        ins.extend([Instruction::PushNumber { value: 1.0 }, Instruction::PushNumber { value: 2.0 }, Instruction::PushNumber { value: 3.0 }, Instruction::MakeList { len: 3 }]);
+        for (pos, params, op) in [(square, &["x"][..], HofOp::Map), (odd, &["x"], HofOp::Keep), (big, &["x"], HofOp::Find), (add, &["a", "b"], HofOp::Combine)] {
+            ins.push(Instruction::DupeValue { top_index: 0 });
+            closure(&mut ins, pos, params);
+            ins.extend([Instruction::ListHof { op }, Instruction::SwapValues { top_index_1: 0, top_index_2: 1 }]);
+        }
+        closure(&mut ins, indexed, &["x", "i", "l"]);
+        ins.push(Instruction::ListHof { op: HofOp::Map });
+
+        for (pos, params, op) in [(big, &["x"][..], HofOp::Find), (add, &["a", "b"], HofOp::Combine)] {
+            ins.push(Instruction::MakeList { len: 0 });
+            closure(&mut ins, pos, params);
+            ins.push(Instruction::ListHof { op });
+        }
+
+        ins.extend([Instruction::PushNumber { value: 1.0 }, Instruction::PushNumber { value: 2.0 }, Instruction::MakeList { len: 2 }, Instruction::PushNumber { value: 3.0 }, Instruction::MakeList { len: 1 }, Instruction::MakeList { len: 2 }]);
+        closure(&mut ins, nested, &["l"]);
+        ins.push(Instruction::ListHof { op: HofOp::Map });
+
+        ins.push(Instruction::MakeList { len: 8 });
+        if warp { ins.push(Instruction::WarpStop) }
+        ins.push(Instruction::Return);
+        ins
+    }
+
+    let system = MockSystem::new();
+    for (warp, expected_yields) in [(false, 9), (true, 0)] {
+        let mut env = get_running_ins(program(warp), SettingsBuilder::default().build().unwrap());
+        run_till_term(&mut env, &system, |mc, _, res| {
+            let (res, yields) = res.unwrap();
+            let expect = Value::from_simple(mc, simple_value!([[1, 4, 9], [1, 3], 2, 6, [11, 22, 33], "", 0, [3, 3]]));
+            assert_values_eq(&res.unwrap(), &expect, 1e-20, "list hof");
+            assert_eq!(yields, expected_yields);
+        });
+    }
+
+    let mut ins = vec![Instruction::PushNumber { value: 1.0 }, Instruction::MakeList { len: 1 }];
+    closure(&mut ins, 0, &[]);
+    ins.extend([Instruction::ListHof { op: HofOp::Map }, Instruction::Return]);
+    let mut env = get_running_ins(ins, SettingsBuilder::default().build().unwrap());
+    run_till_term(&mut env, &system, |_, _, res| match res {
+        Err(ExecError { cause: ErrorCause::HofArgCount { min: 1, max: 3, got: 0 }, .. }) => (),
+        x => panic!("{:?}", x.map(|x| x.0)),
+    });
+
+    let mut ins = vec![Instruction::PushNumber { value: 1.0 }, Instruction::MakeList { len: 1 }];
+    closure(&mut ins, 0, &["a", "b", "c", "d", "e"]);
+    ins.extend([Instruction::ListHof { op: HofOp::Combine }, Instruction::Return]);
+    let mut env = get_running_ins(ins, SettingsBuilder::default().build().unwrap());
+    run_till_term(&mut env, &system, |_, _, res| match res {
+        Err(ExecError { cause: ErrorCause::HofArgCount { min: 2, max: 4, got: 5 }, .. }) => (),
+        x => panic!("{:?}", x.map(|x| x.0)),
+    });
+
+    let mut env = get_running_ins(program(false), SettingsBuilder::default().max_call_depth(1).build().unwrap());
+    run_till_term(&mut env, &system, |_, _, res| match res {
+        Err(ExecError { cause: ErrorCause::CallDepthLimit { limit: 1 }, .. }) => (),
+        x => panic!("{:?}", x.map(|x| x.0)),
+    });
+}
+
+#[test]
+fn test_proc_list_hof_blocks() {
+    let system = MockSystem::new();
+    let mut env = get_running_proc(&format!(include_str!("templates/generic-static.xml"),
+        globals = "",
+        fields = "",
+        funcs = include_str!("blocks/list-hof-blocks.xml"),
+        methods = "",
+    ), SettingsBuilder::default().build().unwrap());
+
+    run_till_term(&mut env, &system, |mc, _, res| {
+        let expect = Value::from_simple(mc, simple_value!([
+            [2, 4, 6], ["a:1", "b:2"],
+            ["5", "7"],
+            "b", "",
+            10, "abc",
+        ]));
+        assert_values_eq(&res.unwrap().0.unwrap(), &expect, 1e-20, "list hof blocks");
+    });
+}
+
+#[test]
+fn test_proc_list_find_cat() {
+    let system = MockSystem::new();
+    let mut env = get_running_proc(&format!(include_str!("templates/generic-static.xml"),
+        globals = "",
+        fields = "",
+        funcs = include_str!("blocks/list-find-cat.xml"),
+        methods = "",
+    ), SettingsBuilder::default().build().unwrap());
+
+    run_till_term(&mut env, &system, |mc, _, res| {
+        let expect = Value::from_simple(mc, simple_value!([
+            ["1", "Foo", "3", ["4", "5"], "x", "1", "Foo", "3", ["4", "5"]],
+            2, 3, 4, 0, true, false,
+        ]));
+        assert_values_eq(&res.unwrap().0.unwrap(), &expect, 1e-20, "list find cat");
+    });
+}
+
+#[test]
+fn test_proc_list_literals() {
+    let system = MockSystem::new();
+    let mut env = get_running_proc(&format!(include_str!("templates/generic-static.xml"),
+        globals = r#"<variable name="g"><list id="7"><item><l>a</l></item><item><list id="8"><item><l>1</l></item><item><list></list></item></list></item></list></variable><variable name="f"><list struct="atomic">4,5</list></variable>"#,
+        fields = "",
+        funcs = include_str!("blocks/list-literals.xml"),
+        methods = "",
+    ), SettingsBuilder::default().build().unwrap());
+
+    run_till_term(&mut env, &system, |mc, _, res| {
+        let expect = Value::from_simple(mc, simple_value!([
+            ["1", ["2", "three"], "x"],
+            ["a", ["1", []], "y"],
+            [4, 5],
+            [],
+        ]));
+        assert_values_eq(&res.unwrap().0.unwrap(), &expect, 1e-20, "list literals");
+    });
+}
+
+#[test]
+fn test_proc_closure_empty_slots() {
+    let system = MockSystem::new();
+    let mut env = get_running_proc(&format!(include_str!("templates/generic-static.xml"),
+        globals = "",
+        fields = "",
+        funcs = include_str!("blocks/closure-empty-slots.xml"),
+        methods = "",
+    ), SettingsBuilder::default().build().unwrap());
+
+    run_till_term(&mut env, &system, |mc, _, res| {
+        let expect = Value::from_simple(mc, simple_value!([15, 12, 36, "!", "out-in?", "a"]));
+        assert_values_eq(&res.unwrap().0.unwrap(), &expect, 1e-20, "closure empty slots");
+    });
+}
+
+#[test]
+fn test_proc_closure_input_list() {
+    let system = MockSystem::new();
+    let mut env = get_running_proc(&format!(include_str!("templates/generic-static.xml"),
+        globals = "",
+        fields = "",
+        funcs = include_str!("blocks/closure-input-list.xml"),
+        methods = "",
+    ), SettingsBuilder::default().build().unwrap());
+
+    run_till_term(&mut env, &system, |mc, _, res| {
+        let expect = Value::from_simple(mc, simple_value!([12, 36, 6, ["hi"]]));
+        assert_values_eq(&res.unwrap().0.unwrap(), &expect, 1e-20, "closure input list");
+    });
+}
+
+#[test]
+fn test_proc_call_closure_dyn() {
+    let system = MockSystem::new();
+    let slot = |index: f64| [Instruction::PushNumber { value: index }, Instruction::PushVariable { var: EMPTY_SLOTS_VAR }, Instruction::ListGet];
+    let make_ins = |args: &[f64]| {
+        let mut ins = vec![Instruction::Jump { to: 9 }];
+        ins.extend([Instruction::PushVariable { var: "a" }, Instruction::PushVariable { var: "b" }, BinaryOp::Sub.into(), Instruction::Return]);
+        ins.extend(slot(1.0));
+        ins.push(Instruction::Return);
+        assert_eq!(ins.len(), 9);
+
+        ins.extend(args.iter().map(|&value| Instruction::PushNumber { value }));
+        ins.push(Instruction::MakeList { len: args.len() });
+        ins.extend([Instruction::MetaPush { value: "a" }, Instruction::MetaPush { value: "b" }, Instruction::MakeClosure { pos: 1, params: 2, captures: 0, empty_slots: 0 }]);
+        ins.push(Instruction::CallClosureDyn);
+
+        ins.extend([Instruction::PushNumber { value: 4.0 }, Instruction::PushNumber { value: 7.0 }, Instruction::MakeList { len: 2 }]);
+        ins.extend([Instruction::MakeClosure { pos: 5, params: 0, captures: 0, empty_slots: 1 }, Instruction::ListHof { op: HofOp::Map }]);
+        ins.extend([Instruction::MakeList { len: 2 }, Instruction::Return]);
+        ins
+    };
+
+    let mut env = get_running_ins(make_ins(&[10.0, 3.0]), SettingsBuilder::default().build().unwrap());
+    run_till_term(&mut env, &system, |mc, _, res| {
+        assert_values_eq(&res.unwrap().0.unwrap(), &Value::from_simple(mc, simple_value!([7, [4, 7]])), 1e-20, "call closure dyn");
+    });
+
+    let mut env = get_running_ins(make_ins(&[10.0, 3.0, 1.0]), SettingsBuilder::default().build().unwrap());
+    run_till_term(&mut env, &system, |_, _, res| match res {
+        Err(ExecError { cause: ErrorCause::ClosureArgCount { expected: 2, got: 3 }, .. }) => (),
+        x => panic!("{x:?}"),
+    });
+}
+
+#[test]
+fn test_proc_list_library() {
+    let system = MockSystem::new();
+    let num = |value: f64| Instruction::PushNumber { value };
+    let string = |value: &'static str| Instruction::PushString { value };
+    let table = || [num(1.0), num(2.0), num(3.0), Instruction::MakeList { len: 3 }, num(4.0), num(5.0), Instruction::MakeList { len: 2 }, string("x"), Instruction::MakeList { len: 3 }];
+
+    let mut ins = vec![Instruction::Jump { to: 5 }];
+    ins.extend([Instruction::PushVariable { var: "a" }, Instruction::PushVariable { var: "b" }, BinaryOp::Greater.into(), Instruction::Return]); // descending
+    assert_eq!(ins.len(), 5);
+
+    ins.extend([num(3.0), string("b"), num(10.0), string("A"), num(-1.0), string("c"), Instruction::MakeList { len: 6 }]);
+    ins.extend([Instruction::DupeValue { top_index: 0 }, Instruction::ListReverse, Instruction::SwapValues { top_index_1: 0, top_index_2: 1 }, Instruction::ListSort]);
+    ins.extend([num(2.0), num(5.0), num(1.0), num(2.0), Instruction::MakeList { len: 4 }]);
+    ins.extend([Instruction::MetaPush { value: "a" }, Instruction::MetaPush { value: "b" }, Instruction::MakeClosure { pos: 1, params: 2, captures: 0, empty_slots: 0 }, Instruction::ListHof { op: HofOp::Sort }]);
+    ins.extend(table());
+    ins.push(Instruction::ListFlatten);
+    ins.extend(table());
+    ins.push(Instruction::ListDims);
+    ins.extend(table());
+    ins.push(Instruction::ListRank);
+    ins.extend(table());
+    ins.push(Instruction::ListColumns);
+    ins.extend(table());
+    ins.extend([num(2.0), num(4.0), Instruction::MakeList { len: 2 }, Instruction::ListReshape]);
+    ins.extend([Instruction::MakeList { len: 0 }, num(2.0), Instruction::ListReshape]);
+    ins.extend([string("a"), num(1.0), string("A"), string("1"), Instruction::MakeList { len: 0 }, Instruction::MakeList { len: 0 }, Instruction::MakeList { len: 6 }, Instruction::ListDistribution]);
+    ins.extend([Instruction::MakeList { len: 10 }, Instruction::Return]);
+
+    let mut env = get_running_ins(ins, SettingsBuilder::default().build().unwrap());
+    run_till_term(&mut env, &system, |mc, _, res| {
+        let expect = Value::from_simple(mc, simple_value!([
+            ["c", -1, "A", 10, "b", 3],
+            [-1, 3, 10, "A", "b", "c"],
+            [5, 2, 2, 1],
+            [1, 2, 3, 4, 5, "x"],
+            [3, 3],
+            2,
+            [[1, 4, "x"], [2, 5, "x"], [3, "", "x"]],
+            [[1, 2, 3, 4], [5, "x", 1, 2]],
+            ["", ""],
+            [["a", 2], [1, 2], [[], 2]],
+        ]));
+        assert_values_eq(&res.unwrap().0.unwrap(), &expect, 1e-20, "list library");
+    });
+}
+
+#[test]
+fn test_proc_list_library_blocks() {
+    let system = MockSystem::new();
+    let mut env = get_running_proc(&format!(include_str!("templates/generic-static.xml"),
+        globals = "",
+        fields = "",
+        funcs = include_str!("blocks/list-library-blocks.xml"),
+        methods = "",
+    ), SettingsBuilder::default().build().unwrap());
+
+    run_till_term(&mut env, &system, |mc, _, res| {
+        let expect = Value::from_simple(mc, simple_value!([
+            ["3", "2", "1"],
+            ["2", "10", "A", "b"],
+            ["3", "2", "1"],
+            ["1", "2", "3"],
+            [2, 2],
+            2,
+            [["1", "3"], ["2", "4"]],
+            [["a", 2], ["b", 1]],
+            [["1", "2", "3"], ["1", "2", "3"]],
+            2,
+            ["1", "2", "3"],
+            true,
+            2,
+        ]));
+        assert_values_eq(&res.unwrap().0.unwrap(), &expect, 1e-20, "list library blocks");
+    });
+}
+
+#[test]
+fn test_proc_list_reshape_limits() {
+    let system = MockSystem::new();
+    let num = |value: f64| Instruction::PushNumber { value };
+    let reshape = |dims: &[f64]| {
+        let mut ins = vec![Instruction::MakeList { len: 0 }];
+        ins.extend(dims.iter().map(|&x| num(x)));
+        ins.extend([Instruction::MakeList { len: dims.len() }, Instruction::ListReshape, Instruction::Return]);
+        ins
+    };
+
+    let mut env = get_running_ins(reshape(&[1e4, 1e4, 1e4]), SettingsBuilder::default().max_list_size(Some(100000)).build().unwrap());
+    run_till_term(&mut env, &system, |_, _, res| match res {
+        Err(ExecError { cause: ErrorCause::ListSizeLimit { size: 100000000, limit: 100000 }, .. }) => (),
+        x => panic!("{:?}", x.map(|x| x.0)),
+    });
+
+    let mut env = get_running_ins(reshape(&[1e10, 1e10, 1e10]), SettingsBuilder::default().build().unwrap());
+    run_till_term(&mut env, &system, |_, _, res| match res {
+        Err(ExecError { cause: ErrorCause::ListSizeLimit { size: usize::MAX, limit: usize::MAX }, .. }) => (),
+        x => panic!("{:?}", x.map(|x| x.0)),
+    });
+
+    let depth = 100000;
+    let mut ins = vec![num(1.0)];
+    ins.extend((0..depth).map(|_| Instruction::MakeList { len: 1 }));
+    ins.extend([Instruction::DupeValue { top_index: 0 }, Instruction::ListRank, Instruction::SwapValues { top_index_1: 0, top_index_2: 1 }, Instruction::ListFlatten]);
+    ins.extend([num(1.0), Instruction::MakeList { len: 1 }]);
+    ins.extend((0..depth).map(|_| num(1.0)));
+    ins.extend([Instruction::MakeList { len: depth }, Instruction::ListReshape, Instruction::ListRank, Instruction::MakeList { len: 3 }, Instruction::Return]);
+
+    let mut env = get_running_ins(ins, SettingsBuilder::default().build().unwrap());
+    run_till_term(&mut env, &system, |mc, _, res| {
+        assert_values_eq(&res.unwrap().0.unwrap(), &Value::from_simple(mc, simple_value!([100000, [1], 100000])), 1e-20, "deep lists");
+    });
+}
+
+#[test]
+fn test_proc_list_sort_mixed() {
+    let system = MockSystem::new();
+    let strings = ["10", "1a", "2", "-3", "B", "a", "1b", "x9", "zz", "0.5"];
+
+    let mut ins = vec![];
+    let mut state = 0x2545f4914f6cdd1du64;
+    for _ in 0..200 {
+        state = state.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+        let pick = (state >> 33) as usize;
+        ins.push(match pick % 3 {
+            0 => Instruction::PushNumber { value: (pick % 41) as f64 - 20.0 },
+            _ => Instruction::PushString { value: strings[pick % strings.len()] },
+        });
+    }
+    ins.extend([Instruction::MakeList { len: 200 }, Instruction::ListSort, Instruction::Return]);
+
+    let mut env = get_running_ins(ins, SettingsBuilder::default().build().unwrap());
+    run_till_term(&mut env, &system, |_, _, res| {
+        let items = match res.unwrap().0.unwrap().to_simple().unwrap() {
+            SimpleValue::List(x) => x,
+            x => panic!("{x:?}"),
+        };
+        assert_eq!(items.len(), 200);
+        let key = |x: &SimpleValue| match x {
+            SimpleValue::Number(x) => (Some(*x), String::new()),
+            SimpleValue::String(x) => match x.parse::<f64>() {
+                Ok(v) => (Some(v), String::new()),
+                Err(_) => (None, x.to_lowercase()),
+            },
+            x => panic!("{x:?}"),
+        };
+        for pair in items.windows(2) {
+            match (key(&pair[0]), key(&pair[1])) {
+                ((Some(a), _), (Some(b), _)) => assert!(a <= b, "{pair:?}"),
+                ((Some(_), _), (None, _)) => (),
+                ((None, _), (Some(_), _)) => panic!("text before number: {pair:?}"),
+                ((None, a), (None, b)) => assert!(a <= b, "{pair:?}"),
+            }
+        }
+    });
+}
+
+#[test]
+fn test_proc_string_library() {
+    let system = MockSystem::new();
+    let mut env = get_running_proc(&format!(include_str!("templates/generic-static.xml"),
+        globals = "",
+        fields = "",
+        funcs = include_str!("blocks/string-library.xml"),
+        methods = "",
+    ), SettingsBuilder::default().build().unwrap());
+
+    run_till_term(&mut env, &system, |mc, _, res| {
+        let expect = Value::from_simple(mc, simple_value!([
+            "e", "", ["a", "c"], "안",
+            "llo w", "hello",
+            true, false,
+            5, 0,
+            "hello", ["AB", "CD"],
+        ]));
+        assert_values_eq(&res.unwrap().0.unwrap(), &expect, 1e-20, "string library");
+    });
+}
+
 #[test]
 fn test_proc_str_cmp_case_insensitive() {
-    let system = StdSystem::new("https://editor.netsblox.org".to_owned(), None);
+    let system = MockSystem::new();
     let mut env = get_running_proc(&format!(include_str!("templates/generic-static.xml"),
         globals = "",
         fields = "",
@@ -479,9 +963,369 @@ fn test_proc_str_cmp_case_insensitive() {
     });
 }
 
+#[test]
+fn test_proc_str_cmp_ordering() {
+    let system = MockSystem::new();
+    let mut env = get_running_proc(&format!(include_str!("templates/generic-static.xml"),
+        globals = "",
+        fields = "",
+        funcs = include_str!("blocks/str-cmp-ordering.xml"),
+        methods = "",
+    ), SettingsBuilder::default().build().unwrap());
+
+    run_till_term(&mut env, &system, |mc, _, res| {
+        let expect = Value::from_simple(mc, simple_value!([
+            true, false, false, false, true, false, false,
+            [true, false, false, false],
+            [true, false, true, true, true, false],
+        ]));
+        assert_values_eq(&res.unwrap().0.unwrap(), &expect, 1e-20, "str cmp ordering");
+    });
+}
+
+#[test]
+fn test_proc_cmp_ops_blocks() {
+    let system = MockSystem::new();
+    let mut env = get_running_proc(&format!(include_str!("templates/generic-static.xml"),
+        globals = "",
+        fields = "",
+        funcs = include_str!("blocks/cmp-ops.xml"),
+        methods = "",
+    ), SettingsBuilder::default().build().unwrap());
+
+    run_till_term(&mut env, &system, |mc, _, res| {
+        let expect = Value::from_simple(mc, simple_value!([
+            true, true, true, true, true, false, true,
+            [true, false, false],
+            false,
+        ]));
+        assert_values_eq(&res.unwrap().0.unwrap(), &expect, 1e-20, "cmp ops blocks");
+    });
+}
+
+#[test]
+fn test_proc_cmp_ops() {
+    let system = MockSystem::new();
+    let cases: [(&str, &str, [bool; 6]); 7] = [
+        // a, b, [a > b, a >= b, a < b, a <= b, a == b, a != b]
+        ("3", "3", [false, true, false, true, true, false]),
+        ("3", "12", [false, false, true, true, false, true]),
+        ("abc", "ABC", [false, true, false, true, true, false]),
+        ("abc", "abd", [false, false, true, true, false, true]),
+        ("Zebra", "apple", [true, true, false, false, false, true]),
+        ("12", "1a", [false, false, true, true, false, true]),
+        ("", "0", [false, false, true, true, false, true]),
+    ];
+    for (a, b, expected) in cases {
+        let mut ins = vec![];
+        for op in [Instruction::from(BinaryOp::Greater), BinaryOp::GreaterEq.into(), BinaryOp::Less.into(), BinaryOp::LessEq.into(), Instruction::Eq, Instruction::Neq] {
+            ins.extend([Instruction::PushString { value: a }, Instruction::PushString { value: b }, op]);
+        }
+        ins.extend([Instruction::MakeList { len: 6 }, Instruction::Return]);
+
+        let mut env = get_running_ins(ins, SettingsBuilder::default().build().unwrap());
+        run_till_term(&mut env, &system, |mc, _, res| {
+            let expect = Value::from_simple(mc, SimpleValue::List(expected.into_iter().map(Into::into).collect()));
+            assert_values_eq(&res.unwrap().0.unwrap(), &expect, 1e-20, &format!("cmp ops {a:?} {b:?}"));
+        });
+    }
+
+    let ins = vec![
+        Instruction::PushNumber { value: 4.0 },
+        Instruction::PushString { value: "a" },
+        Instruction::MakeList { len: 2 },
+        Instruction::PushNumber { value: 4.0 },
+        BinaryOp::GreaterEq.into(),
+        Instruction::Return,
+    ];
+    let mut env = get_running_ins(ins, SettingsBuilder::default().build().unwrap());
+    run_till_term(&mut env, &system, |mc, _, res| {
+        assert_values_eq(&res.unwrap().0.unwrap(), &Value::from_simple(mc, simple_value!([true, true])), 1e-20, "cmp ops hyperized");
+    });
+}
+
+#[test]
+fn test_proc_extended_math() {
+    let system = MockSystem::new();
+    let ins = vec![
+        Instruction::PushNumber { value: -7.5 },
+        Instruction::PushNumber { value: 0.0 },
+        Instruction::PushString { value: "12" },
+        Instruction::MakeList { len: 3 },
+        UnaryOp::Sign.into(),
+        Instruction::PushNumber { value: 1.0 },
+        Instruction::PushNumber { value: -1.0 },
+        BinaryOp::Atan2.into(),
+        Instruction::PushNumber { value: 3.0 },
+        Instruction::PushNumber { value: 8.0 },
+        Instruction::MakeList { len: 2 },
+        Instruction::PushNumber { value: 5.0 },
+        BinaryOp::Min.into(),
+        Instruction::PushNumber { value: 3.0 },
+        Instruction::PushNumber { value: 8.0 },
+        Instruction::MakeList { len: 2 },
+        Instruction::PushString { value: "5" },
+        BinaryOp::Max.into(),
+        Instruction::MakeList { len: 4 },
+        Instruction::Return,
+    ];
+    let mut env = get_running_ins(ins, SettingsBuilder::default().build().unwrap());
+    run_till_term(&mut env, &system, |mc, _, res| {
+        let expect = Value::from_simple(mc, simple_value!([[-1.0, 0.0, 1.0], 135.0, [3.0, 5.0], [5.0, 8.0]]));
+        assert_values_eq(&res.unwrap().0.unwrap(), &expect, 1e-10, "extended math");
+    });
+}
+
+#[test]
+fn test_proc_extended_math_blocks() {
+    let system = MockSystem::new();
+    let mut env = get_running_proc(&format!(include_str!("templates/generic-static.xml"),
+        globals = "",
+        fields = "",
+        funcs = include_str!("blocks/math-type-blocks.xml"),
+        methods = "",
+    ), SettingsBuilder::default().build().unwrap());
+
+    run_till_term(&mut env, &system, |mc, _, res| {
+        let expect = Value::from_simple(mc, simple_value!([
+            1.0, 3.0, 100.0, [-1.0, 0.0, 1.0], 135.0, [3.0, 5.0], [5.0, 8.0],
+            [true, false, false, false, false, true, false, false, false, false, true, false, false, false, false, true],
+        ]));
+        assert_values_eq(&res.unwrap().0.unwrap(), &expect, 1e-10, "extended math blocks");
+    });
+}
+
+#[test]
+fn test_proc_random() {
+    let system = MockSystem::new();
+    let ins = || vec![
+        Instruction::PushNumber { value: 10.0 },
+        Instruction::PushNumber { value: 1.0 },
+        Instruction::Random,
+        Instruction::PushNumber { value: 0.5 },
+        Instruction::PushNumber { value: 1.0 },
+        Instruction::Random,
+        Instruction::PushNumber { value: 4.0 },
+        Instruction::PushNumber { value: 4.0 },
+        Instruction::Random,
+        Instruction::MakeList { len: 3 },
+        Instruction::Return,
+    ];
+    for _ in 0..64 {
+        let mut env = get_running_ins(ins(), SettingsBuilder::default().build().unwrap());
+        run_till_term(&mut env, &system, |_, _, res| {
+            let res = res.unwrap().0.unwrap().as_list().unwrap();
+            let res = res.read().iter().map(|x| x.to_number().unwrap()).collect::<Vec<_>>();
+            assert!((1.0..=10.0).contains(&res[0]) && res[0].fract() == 0.0, "{res:?}");
+            assert!((0.5..1.0).contains(&res[1]), "{res:?}");
+            assert_eq!(res[2], 4.0);
+        });
+    }
+}
+
+#[test]
+fn test_proc_type_query() {
+    let system = MockSystem::new();
+    let mut ins = vec![];
+    for ty in [BasicType::Number, BasicType::Text, BasicType::Bool, BasicType::List] {
+        ins.extend([
+            Instruction::PushNumber { value: 4.0 },
+            Instruction::TypeQuery { ty },
+            Instruction::PushString { value: "4" },
+            Instruction::TypeQuery { ty },
+            Instruction::PushString { value: "four" },
+            Instruction::TypeQuery { ty },
+            Instruction::PushBool { value: true },
+            Instruction::TypeQuery { ty },
+            Instruction::PushNumber { value: 1.0 },
+            Instruction::MakeList { len: 1 },
+            Instruction::TypeQuery { ty },
+            Instruction::MakeList { len: 5 },
+        ]);
+    }
+    ins.extend([Instruction::MakeList { len: 4 }, Instruction::Return]);
+    let mut env = get_running_ins(ins, SettingsBuilder::default().build().unwrap());
+    run_till_term(&mut env, &system, |mc, _, res| {
+        let expect = Value::from_simple(mc, simple_value!([
+            [true, true, false, false, false],
+            [false, false, true, false, false],
+            [false, false, false, true, false],
+            [false, false, false, false, true],
+        ]));
+        assert_values_eq(&res.unwrap().0.unwrap(), &expect, 1e-20, "type query");
+    });
+}
+
+#[test]
+fn test_proc_number_format() {
+    let system = MockSystem::new();
+    let ins = vec![
+        Instruction::PushNumber { value: 0.1 },
+        Instruction::PushNumber { value: 0.2 },
+        BinaryOp::Add.into(),
+        Instruction::PushString { value: " " },
+        Instruction::PushNumber { value: 1e21 },
+        Instruction::PushString { value: " " },
+        Instruction::PushNumber { value: 4.0 },
+        Instruction::PushString { value: " " },
+        Instruction::PushNumber { value: -0.0 },
+        Instruction::PushString { value: " " },
+        Instruction::PushNumber { value: 2.5e-7 },
+        Instruction::Strcat { args: 9 },
+        Instruction::PushNumber { value: 1e21 },
+        Instruction::PushString { value: "1e+21" },
+        Instruction::Eq,
+        Instruction::MakeList { len: 2 },
+        Instruction::Return,
+    ];
+    let mut env = get_running_ins(ins, SettingsBuilder::default().build().unwrap());
+    run_till_term(&mut env, &system, |mc, _, res| {
+        let res = res.unwrap().0.unwrap();
+        assert_values_eq(&res, &Value::from_simple(mc, simple_value!(["0.30000000000000004 1e+21 4 0 2.5e-7", true])), 1e-20, "number format");
+        assert_eq!(format!("{res:?}"), r#"["0.30000000000000004 1e+21 4 0 2.5e-7",true]"#);
+
+        let json: Json = simple_value!([4.0, -2.0, 0.5]).try_into().unwrap();
+        assert_eq!(json.to_string(), "[4,-2,0.5]");
+    });
+}
+
+#[test]
+fn test_proc_csv_json() {
+    let system = MockSystem::new();
+    let table = [["a,b", "c\"d", "4"], ["e\nf", "", "x y"], ["", "", ""]];
+    let push_table = |ins: &mut Vec<Instruction<'static>>| {
+        for row in table {
+            ins.extend(row.into_iter().map(|value| Instruction::PushString { value }));
+            ins.push(Instruction::MakeList { len: row.len() });
+        }
+        ins.push(Instruction::MakeList { len: table.len() });
+    };
+
+    let mut ins = vec![
+        Instruction::PushString { value: "name,quote\r\n\"Smith, J\",\"said \"\"hi\"\"\"\n\"multi\nline\",\n" },
+        UnaryOp::SplitCsv.into(),
+        Instruction::PushString { value: "x,\"\",y" },
+        UnaryOp::SplitCsv.into(),
+    ];
+    push_table(&mut ins);
+    ins.push(Instruction::ListCsv);
+    push_table(&mut ins);
+    ins.extend([Instruction::ListCsv, UnaryOp::SplitCsv.into()]);
+    push_table(&mut ins);
+    ins.extend([Instruction::ListJson, UnaryOp::SplitJson.into()]);
+    ins.extend([
+        Instruction::PushString { value: "x" },
+        Instruction::PushNumber { value: 2.5 },
+        Instruction::PushBool { value: true },
+        Instruction::MakeList { len: 3 },
+        Instruction::ListCsv,
+        Instruction::PushString { value: "x" },
+        Instruction::PushNumber { value: 2.0 },
+        Instruction::PushBool { value: true },
+        Instruction::MakeList { len: 0 },
+        Instruction::MakeList { len: 4 },
+        Instruction::ListJson,
+    ]);
+    let one_row = || [Instruction::PushString { value: "a" }, Instruction::PushString { value: "b" }, Instruction::MakeList { len: 2 }, Instruction::MakeList { len: 1 }];
+    ins.extend(one_row());
+    ins.push(Instruction::ListCsv);
+    ins.extend(one_row());
+    ins.extend([
+        Instruction::ListCsv,
+        UnaryOp::SplitCsv.into(),
+        Instruction::PushString { value: "" },
+        Instruction::MakeList { len: 1 },
+        Instruction::MakeList { len: 1 },
+        Instruction::ListCsv,
+        UnaryOp::SplitCsv.into(),
+        Instruction::PushString { value: "" },
+        Instruction::MakeList { len: 1 },
+        Instruction::ListCsv,
+        UnaryOp::SplitCsv.into(),
+        Instruction::MakeList { len: 11 },
+        Instruction::Return,
+    ]);
+
+    let mut env = get_running_ins(ins, SettingsBuilder::default().build().unwrap());
+    run_till_term(&mut env, &system, |mc, _, res| {
+        let table = || SimpleValue::List(table.into_iter().map(|row| SimpleValue::List(row.into_iter().map(|x| x.to_owned().into()).collect())).collect());
+        let expect = Value::from_simple(mc, SimpleValue::List(vec![
+            simple_value!([["name", "quote"], ["Smith, J", "said \"hi\""], ["multi\nline", ""]]),
+            simple_value!(["x", "", "y"]),
+            "\"a,b\",\"c\"\"d\",4\n\"e\nf\",,x y\n,,\n".to_owned().into(),
+            table(),
+            table(),
+            "x,2.5,true".to_owned().into(),
+            "[\"x\",2,true,[]]".to_owned().into(),
+            "a,b\n".to_owned().into(),
+            simple_value!([["a", "b"]]),
+            simple_value!([[""]]),
+            simple_value!([""]),
+        ]));
+        assert_values_eq(&res.unwrap().0.unwrap(), &expect, 1e-20, "csv json");
+    });
+}
+
+#[test]
+fn test_proc_csv_json_blocks() {
+    let system = MockSystem::new();
+    let mut env = get_running_proc(&format!(include_str!("templates/generic-static.xml"),
+        globals = "",
+        fields = "",
+        funcs = include_str!("blocks/csv-json-blocks.xml"),
+        methods = "",
+    ), SettingsBuilder::default().build().unwrap());
+
+    run_till_term(&mut env, &system, |mc, _, res| {
+        let expect = Value::from_simple(mc, simple_value!([
+            "a,b\nc,d\n",
+            [["a", "b,c"]],
+            "[\"x\",[\"y\"]]",
+            [["a", "b,c"]],
+        ]));
+        assert_values_eq(&res.unwrap().0.unwrap(), &expect, 1e-20, "csv json blocks");
+    });
+}
+
+#[test]
+fn test_proc_rpc_json_objects() {
+    for (strategy, expected) in [
+        (JsonObjectStrategy::TextKeys, json!({ "name": "robot", "pos": [[1, 2], [3, 4]] })),
+        (JsonObjectStrategy::Never, json!([["name", "robot"], ["pos", [[1, 2], [3, 4]]]])),
+    ] {
+        let system = MockSystem::new();
+        system.add_response("Test", "echo", None, 0, Ok(json!({ "a": 1 })));
+        let ins = vec![
+            Instruction::MetaPush { value: "data" },
+            Instruction::PushString { value: "name" },
+            Instruction::PushString { value: "robot" },
+            Instruction::MakeList { len: 2 },
+            Instruction::PushString { value: "pos" },
+            Instruction::PushNumber { value: 1.0 },
+            Instruction::PushNumber { value: 2.0 },
+            Instruction::MakeList { len: 2 },
+            Instruction::PushNumber { value: 3.0 },
+            Instruction::PushNumber { value: 4.0 },
+            Instruction::MakeList { len: 2 },
+            Instruction::MakeList { len: 2 },
+            Instruction::MakeList { len: 2 },
+            Instruction::MakeList { len: 2 },
+            Instruction::CallRpc { service: "Test", rpc: "echo", args: 1 },
+            Instruction::Return,
+        ];
+        let mut env = get_running_ins(ins, SettingsBuilder::default().json_objects(strategy).build().unwrap());
+        run_till_term(&mut env, &system, |mc, _, res| {
+            assert_values_eq(&res.unwrap().0.unwrap(), &Value::from_simple(mc, simple_value!([["a", 1]])), 1e-20, "rpc json result");
+        });
+        let log = system.call_log();
+        assert_eq!(log.len(), 1);
+        assert_eq!(log[0].args, [("data".to_owned(), expected)]);
+    }
+}
+
 #[test]
 fn test_proc_rpc_call_basic() {
-    let system = StdSystem::new("https://editor.netsblox.org".to_owned(), None);
+    let system = MockSystem::new();
     let mut env = get_running_proc(&format!(include_str!("templates/generic-static.xml"),
         globals = "",
         fields = "",
@@ -489,7 +1333,12 @@ fn test_proc_rpc_call_basic() {
         methods = "",
     ), SettingsBuilder::default().build().unwrap());
 
-    for (lat, long, city) in [(36.1627, -86.7816, "Nashville"), (40.8136, -96.7026, "Lincoln"), (40.7608, -111.8910, "Salt Lake City")] {
+    let cities = [(36.1627, -86.7816, "Nashville"), (40.8136, -96.7026, "Lincoln"), (40.7608, -111.8910, "Salt Lake City")];
+    for (i, (lat, long, city)) in cities.into_iter().enumerate() {
+        system.add_response("Geolocation", "city", Some(&[("latitude", json!(lat)), ("longitude", json!(long))]), 100 * i as u64, Ok(json!(city)));
+    }
+
+    for (lat, long, city) in cities {
         env.mutate(|mc, env| {
             let mut locals = SymbolTable::default();
             locals.redefine_or_define("lat", Shared::Unique(lat.into()));
@@ -501,11 +1350,414 @@ fn test_proc_rpc_call_basic() {
             x => panic!("{:?}", x),
         });
     }
+
+    let log = system.call_log();
+    assert_eq!(log.len(), 3);
+    assert_eq!(log.iter().map(|x| x.time).collect::<Vec<_>>(), [0, 0, 100]);
+    for (call, (lat, long, _)) in iter::zip(&log, cities) {
+        assert_eq!((call.service.as_str(), call.rpc.as_str()), ("Geolocation", "city"));
+        assert_eq!(call.args, [("latitude".to_owned(), json!(lat)), ("longitude".to_owned(), json!(long))]);
+    }
+
+    system.add_response("Geolocation", "city", None, 50, Err("bad coordinates".to_owned()));
+    env.mutate(|mc, env| {
+        let mut locals = SymbolTable::default();
+        locals.redefine_or_define("lat", Shared::Unique(1.0.into()));
+        locals.redefine_or_define("long", Shared::Unique(2.0.into()));
+        env.proc.write(mc).initialize(locals, None);
+    });
+    run_till_term(&mut env, &system, |_, _, res| match res.unwrap().0.unwrap() {
+        Value::String(ret) => assert_eq!(&*ret, "bad coordinates"),
+        x => panic!("{:?}", x),
+    });
+    assert_eq!(system.time_ms().unwrap(), 350);
+}
+
+#[test]
+fn test_proc_rpc_call_image() {
+    let system = MockSystem::new();
+    let mut env = get_running_proc(&format!(include_str!("templates/generic-static.xml"),
+        globals = "",
+        fields = "",
+        funcs = include_str!("blocks/rpc-call-image.xml"),
+        methods = "",
+    ), SettingsBuilder::default().build().unwrap());
+
+    let image = Blob { content_type: "image/png".into(), data: b"\x89PNG\r\n\x1a\n".to_vec() };
+    system.add_blob_response("GoogleMaps", "getMap", None, 20, image.clone());
+    system.add_response("PhoneIoT", "setImage", Some(&[("device", json!("phone")), ("id", json!("canvas")), ("img", json!("data:image/png;base64,iVBORw0KGgo="))]), 5, Ok(json!("OK")));
+
+    env.mutate(|mc, env| {
+        let mut locals = SymbolTable::default();
+        locals.redefine_or_define("lat", Shared::Unique(36.1627.into()));
+        locals.redefine_or_define("long", Shared::Unique((-86.7816).into()));
+        env.proc.write(mc).initialize(locals, None);
+    });
+    run_till_term(&mut env, &system, |_, _, res| {
+        let res = res.unwrap().0.unwrap();
+        assert_eq!(res.to_simple().unwrap(), SimpleValue::List(vec![SimpleValue::Blob(image.clone()), simple_value!("OK")]));
+        match &res.as_list().unwrap().read()[0] {
+            Value::Blob(x) => {
+                assert!(x.is_image());
+                assert_eq!(format!("{:?}", &**x), "Blob(image/png, 8 bytes)");
+            }
+            x => panic!("{:?}", x),
+        }
+    });
+    assert_eq!(system.call_log().len(), 2);
+    assert_eq!(system.time_ms().unwrap(), 25);
+}
+
+#[test]
+fn test_proc_rpc_timeout() {
+    let system = MockSystem::new();
+    let mut env = get_running_proc(&format!(include_str!("templates/generic-static.xml"),
+        globals = "",
+        fields = "",
+        funcs = include_str!("blocks/rpc-call-basic.xml"),
+        methods = "",
+    ), SettingsBuilder::default().rpc_timeout(Some(100)).build().unwrap());
+    system.add_response("Geolocation", "city", None, 1000, Ok(json!("Nashville")));
+
+    for lat in [1.0, 2.0] {
+        env.mutate(|mc, env| {
+            let mut locals = SymbolTable::default();
+            locals.redefine_or_define("lat", Shared::Unique(lat.into()));
+            locals.redefine_or_define("long", Shared::Unique(2.0.into()));
+            env.proc.write(mc).initialize(locals, None);
+        });
+        run_till_term(&mut env, &system, |_, _, res| match res.unwrap().0.unwrap() {
+            Value::String(ret) => assert_eq!(&*ret, "RPC timed out"),
+            x => panic!("{:?}", x),
+        });
+        assert_eq!(system.pending_rpcs(), 0);
+    }
+    assert_eq!(system.time_ms().unwrap(), 200);
+
+    system.add_response("Geolocation", "city", None, 50, Ok(json!("Nashville")));
+    env.mutate(|mc, env| {
+        let mut locals = SymbolTable::default();
+        locals.redefine_or_define("lat", Shared::Unique(1.0.into()));
+        locals.redefine_or_define("long", Shared::Unique(2.0.into()));
+        let mut proc = env.proc.write(mc);
+        proc.initialize(locals, None);
+        while system.pending_rpcs() == 0 {
+            proc.step(mc, &system).unwrap();
+        }
+        proc.initialize(Default::default(), None);
+        assert_eq!(system.pending_rpcs(), 1);
+        let _ = proc.step(mc, &system);
+        assert_eq!(system.pending_rpcs(), 0);
+    });
+
+    // dropping a process which is awaiting an RPC reports the key to its queue of orphaned rpcs
+    system.add_response("Geolocation", "city", None, 50, Ok(json!("Nashville")));
+    let orphans = env.mutate(|mc, env| {
+        let mut locals = SymbolTable::default();
+        locals.redefine_or_define("lat", Shared::Unique(1.0.into()));
+        locals.redefine_or_define("long", Shared::Unique(2.0.into()));
+        let mut proc = env.proc.write(mc);
+        proc.initialize(locals, None);
+        while system.pending_rpcs() == 0 {
+            proc.step(mc, &system).unwrap();
+        }
+        proc.orphaned_rpcs()
+    });
+    drop(env);
+    assert_eq!(system.pending_rpcs(), 1);
+    orphans.cancel_all(&system);
+    assert_eq!(system.pending_rpcs(), 0);
+}
+
+#[test]
+fn test_proc_rpc_call_offline() {
+    let system = StdSystem::new(StdSystemConfigBuilder::default().offline(true).build().unwrap()).unwrap();
+    match system.call_rpc("Geolocation".to_owned(), "city".to_owned(), vec![]) {
+        Err(SystemError::NotSupported { feature: SystemFeature::Rpc }) => (),
+        x => panic!("{:?}", x.err()),
+    }
+
+    let system = MockSystem::new();
+    let mut env = get_running_proc(&format!(include_str!("templates/generic-static.xml"),
+        globals = "",
+        fields = "",
+        funcs = include_str!("blocks/rpc-call-basic.xml"),
+        methods = "",
+    ), SettingsBuilder::default().build().unwrap());
+    env.mutate(|mc, env| {
+        let mut locals = SymbolTable::default();
+        locals.redefine_or_define("lat", Shared::Unique(36.1627.into()));
+        locals.redefine_or_define("long", Shared::Unique((-86.7816).into()));
+        env.proc.write(mc).initialize(locals, None);
+    });
+    run_till_term(&mut env, &system, |_, _, res| match res {
+        Err(ExecError { cause: ErrorCause::SystemError { error: SystemError::Other { .. } }, .. }) => (),
+        x => panic!("{:?}", x.map(|x| x.0)),
+    });
+    assert_eq!(system.call_log().len(), 1);
+}
+
+#[test]
+fn test_proc_rpc_metadata() {
+    let xml = format!(include_str!("templates/generic-static.xml"),
+        globals = "",
+        fields = "",
+        funcs = include_str!("blocks/rpc-call-basic.xml"),
+        methods = "",
+    );
+    let geolocation = ServiceMetadata::from_json(&json!({
+        "description": "geolocation services",
+        "rpcs": {
+            "city": { "args": [{ "name": "latitude", "type": { "name": "Latitude" } }, { "name": "longitude", "type": { "name": "Longitude" } }] },
+            "nearby": { "args": [{ "name": "latitude" }, { "name": "longitude" }, { "name": "keyword", "optional": true }] },
+        },
+    })).unwrap();
+    assert_eq!(geolocation.rpcs.len(), 2);
+    assert_eq!(geolocation.rpcs["nearby"].args[2], RpcArgMetadata { name: "keyword".into(), ty: None, optional: true });
+    assert!(ServiceMetadata::from_json(&json!({ "rpcs": [] })).is_err());
+    assert!(ServiceMetadata::parse_all(&json!({ "Geolocation": { "rpcs": {} } })).unwrap()["Geolocation"].rpcs.is_empty());
+
+    let system = MockSystem::new();
+    system.add_response("Geolocation", "city", None, 0, Ok(json!("Nashville")));
+
+    let (code, _) = ByteCode::compile(&ast::ParserBuilder::default().build().unwrap().parse(&xml).unwrap().roles[0]);
+    assert_eq!(code.check_rpcs(&system), []);
+    system.set_service_metadata("Geolocation", geolocation.clone());
+    assert_eq!(code.check_rpcs(&system), []);
+
+    let mut env = get_running_proc(&xml, SettingsBuilder::default().build().unwrap());
+    type Case = (Result<f64, &'static str>, Result<(), RpcCheckError>);
+    let cases: [Case; 3] = [
+        (Ok(36.1627), Ok(())),
+        (Err("north"), Err(RpcCheckError::ArgType { service: "Geolocation".into(), rpc: "city".into(), arg: "latitude".into(), expected: "Latitude".into() })),
+        (Err(""), Err(RpcCheckError::MissingArg { service: "Geolocation".into(), rpc: "city".into(), arg: "latitude".into() })),
+    ];
+    for (lat, expected) in cases {
+        env.mutate(|mc, env| {
+            let lat = match lat {
+                Ok(x) => x.into(),
+                Err(x) => Value::String(Gc::allocate(mc, x.to_owned())),
+            };
+            let mut locals = SymbolTable::default();
+            locals.redefine_or_define("lat", Shared::Unique(lat));
+            locals.redefine_or_define("long", Shared::Unique((-86.7816).into()));
+            env.proc.write(mc).initialize(locals, None);
+        });
+        run_till_term(&mut env, &system, |_, _, res| match (res, expected) {
+            (Ok((Some(Value::String(x)), _)), Ok(())) => assert_eq!(&*x, "Nashville"),
+            (Err(ExecError { cause: ErrorCause::RpcCheck { error }, .. }), Err(expected)) => assert_eq!(error, expected),
+            (x, _) => panic!("{:?}", x.map(|x| x.0)),
+        });
+    }
+    assert_eq!(system.call_log().len(), 1);
+
+    let mut renamed = geolocation.clone();
+    renamed.rpcs.get_mut("city").unwrap().args[1].name = "long".into();
+    system.set_service_metadata("Geolocation", renamed);
+    let errors = code.check_rpcs(&system);
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].1, RpcCheckError::UnknownArg { service: "Geolocation".into(), rpc: "city".into(), arg: "longitude".into() });
+
+    let mut missing = geolocation;
+    missing.rpcs.get_mut("city").unwrap().args.push(RpcArgMetadata { name: "radius".into(), ty: Some("Number".into()), optional: false });
+    system.set_service_metadata("Geolocation", missing.clone());
+    assert_eq!(code.check_rpcs(&system)[0].1, RpcCheckError::MissingArg { service: "Geolocation".into(), rpc: "city".into(), arg: "radius".into() });
+
+    missing.rpcs.remove("city");
+    system.set_service_metadata("Geolocation", missing);
+    assert_eq!(code.check_rpcs(&system)[0].1, RpcCheckError::UnknownRpc { service: "Geolocation".into(), rpc: "city".into() });
+    env.mutate(|mc, env| {
+        let mut locals = SymbolTable::default();
+        locals.redefine_or_define("lat", Shared::Unique(36.1627.into()));
+        locals.redefine_or_define("long", Shared::Unique((-86.7816).into()));
+        env.proc.write(mc).initialize(locals, None);
+    });
+    run_till_term(&mut env, &system, |_, _, res| match res {
+        Err(ExecError { cause: ErrorCause::RpcCheck { error: RpcCheckError::UnknownRpc { .. } }, .. }) => (),
+        x => panic!("{:?}", x.map(|x| x.0)),
+    });
+    assert_eq!(system.call_log().len(), 1);
+}
+
+#[test]
+fn test_proc_rpc_permissions() {
+    let xml = format!(include_str!("templates/generic-static.xml"),
+        globals = "",
+        fields = "",
+        funcs = include_str!("blocks/rpc-call-basic.xml"),
+        methods = "",
+    );
+    let system = MockSystem::new();
+    system.add_response("Geolocation", "city", None, 0, Ok(json!("Nashville")));
+
+    let denied = RpcPermissionError::Denied { service: "Geolocation".into(), rpc: "city".into() };
+    let limited = RpcPermissionError::RateLimited { service: "Geolocation".into(), rpc: "city".into(), max_calls: 2, period: 1000 };
+    type Case<'a> = (RpcPermissions, &'a [(u64, Result<(), RpcPermissionError>)]);
+    let cases: [Case; 5] = [
+        (RpcPermissions::new(), &[(0, Ok(()))]),
+        (RpcPermissions::new().deny("Geolocation", None), &[(0, Err(denied.clone()))]),
+        (RpcPermissions::new().allow("Geolocation", None).deny("Geolocation", Some("city")), &[(0, Err(denied.clone()))]),
+        (RpcPermissions::new().allow("PhoneIoT", None).allow("Geolocation", Some("nearby")), &[(0, Err(denied.clone()))]),
+        (RpcPermissions::new().allow("Geolocation", Some("city")).rate_limit("Geolocation", 2, 1000), &[
+            (0, Ok(())), (400, Ok(())), (900, Err(limited.clone())), (1000, Ok(())), (1100, Err(limited.clone())), (1400, Ok(())),
+        ]),
+    ];
+    let mut expected_calls = 0;
+    for (permissions, calls) in cases {
+        let mut env = get_running_proc(&xml, SettingsBuilder::default().rpc_permissions(Some(Rc::new(permissions))).build().unwrap());
+        for (time, expected) in calls {
+            system.advance_to(*time);
+            if expected.is_ok() { expected_calls += 1 }
+            env.mutate(|mc, env| {
+                let mut locals = SymbolTable::default();
+                locals.redefine_or_define("lat", Shared::Unique(36.1627.into()));
+                locals.redefine_or_define("long", Shared::Unique((-86.7816).into()));
+                env.proc.write(mc).initialize(locals, None);
+            });
+            run_till_term(&mut env, &system, |_, _, res| match (res, expected) {
+                (Ok((Some(Value::String(x)), _)), Ok(())) => assert_eq!(&*x, "Nashville"),
+                (Err(ExecError { cause: ErrorCause::RpcPermission { error }, .. }), Err(expected)) => assert_eq!(error, *expected),
+                (x, _) => panic!("{:?}", x.map(|x| x.0)),
+            });
+            assert_eq!(system.call_log().len(), expected_calls);
+        }
+    }
+}
+
+#[test]
+fn test_proc_rpc_call_dynamic() {
+    let system = MockSystem::new();
+    system.add_response("Geolocation", "city", None, 10, Ok(json!("Nashville")));
+
+    let mut env = get_running_ins(vec![
+        Instruction::PushString { value: "Geolocation" },
+        Instruction::PushString { value: "city" },
+        Instruction::PushString { value: "latitude" },
+        Instruction::PushNumber { value: 36.1627 },
+        Instruction::MakeList { len: 2 },
+        Instruction::PushString { value: "longitude" },
+        Instruction::PushNumber { value: -86.7816 },
+        Instruction::MakeList { len: 2 },
+        Instruction::MakeList { len: 2 },
+        Instruction::CallRpcDyn,
+        Instruction::Return,
+    ], SettingsBuilder::default().build().unwrap());
+    run_till_term(&mut env, &system, |_, _, res| match res.unwrap().0.unwrap() {
+        Value::String(x) => assert_eq!(&*x, "Nashville"),
+        x => panic!("{:?}", x),
+    });
+    let log = system.call_log();
+    assert_eq!(log.len(), 1);
+    assert_eq!((log[0].service.as_str(), log[0].rpc.as_str(), log[0].time), ("Geolocation", "city", 0));
+    assert_eq!(log[0].args, [("latitude".to_owned(), json!(36.1627)), ("longitude".to_owned(), json!(-86.7816))]);
+
+    let mut env = get_running_ins(vec![
+        Instruction::PushString { value: "Geolocation" },
+        Instruction::PushString { value: "city" },
+        Instruction::PushString { value: "latitude" },
+        Instruction::PushNumber { value: 36.1627 },
+        Instruction::MakeList { len: 2 },
+        Instruction::PushString { value: "longitude" },
+        Instruction::MakeList { len: 1 },
+        Instruction::MakeList { len: 2 },
+        Instruction::CallRpcDyn,
+        Instruction::Return,
+    ], SettingsBuilder::default().build().unwrap());
+    run_till_term(&mut env, &system, |_, _, res| match res {
+        Err(ExecError { cause: ErrorCause::RpcArgNotPair { index: 2 }, .. }) => (),
+        x => panic!("{:?}", x.map(|x| x.0)),
+    });
+
+    let mut env = get_running_ins(vec![
+        Instruction::PushString { value: "Geolocation" },
+        Instruction::PushString { value: "city" },
+        Instruction::PushNumber { value: 5.0 },
+        Instruction::CallRpcDyn,
+        Instruction::Return,
+    ], SettingsBuilder::default().build().unwrap());
+    run_till_term(&mut env, &system, |_, _, res| match res {
+        Err(ExecError { cause: ErrorCause::ConversionError { got: Type::Number, expected: Type::List }, .. }) => (),
+        x => panic!("{:?}", x.map(|x| x.0)),
+    });
+    assert_eq!(system.call_log().len(), 1);
+}
+
+#[test]
+fn test_proc_rpc_call_dynamic_blocks() {
+    let system = MockSystem::new();
+    let mut env = get_running_proc(&format!(include_str!("templates/generic-static.xml"),
+        globals = "",
+        fields = "",
+        funcs = include_str!("blocks/rpc-call-dynamic.xml"),
+        methods = "",
+    ), SettingsBuilder::default().build().unwrap());
+
+    system.add_response("Geolocation", "city", None, 10, Ok(json!("Nashville")));
+    env.mutate(|mc, env| {
+        let mut locals = SymbolTable::default();
+        locals.redefine_or_define("service", Shared::Unique(Gc::allocate(mc, "Geolocation".to_owned()).into()));
+        locals.redefine_or_define("rpc", Shared::Unique(Gc::allocate(mc, "city".to_owned()).into()));
+        locals.redefine_or_define("lat", Shared::Unique(36.1627.into()));
+        locals.redefine_or_define("long", Shared::Unique((-86.7816).into()));
+        env.proc.write(mc).initialize(locals, None);
+    });
+    run_till_term(&mut env, &system, |_, _, res| match res.unwrap().0.unwrap() {
+        Value::String(x) => assert_eq!(&*x, "Nashville"),
+        x => panic!("{:?}", x),
+    });
+
+    let log = system.call_log();
+    assert_eq!(log.len(), 1);
+    assert_eq!((log[0].service.as_str(), log[0].rpc.as_str()), ("Geolocation", "city"));
+    assert_eq!(log[0].args, [("latitude".to_owned(), json!(36.1627)), ("longitude".to_owned(), json!(-86.7816))]);
+}
+
+#[test]
+fn test_proc_rpc_run_blocks() {
+    let system = MockSystem::new();
+    let mut env = get_running_proc(&format!(include_str!("templates/generic-static.xml"),
+        globals = "",
+        fields = "",
+        funcs = include_str!("blocks/rpc-run.xml"),
+        methods = "",
+    ), SettingsBuilder::default().build().unwrap());
+
+    system.add_response("Geolocation", "city", None, 10, Ok(json!("Nashville")));
+    env.mutate(|mc, env| {
+        let mut locals = SymbolTable::default();
+        locals.redefine_or_define("service", Shared::Unique(Gc::allocate(mc, "Geolocation".to_owned()).into()));
+        env.proc.write(mc).initialize(locals, None);
+    });
+    run_till_term(&mut env, &system, |_, _, res| match res.unwrap().0.unwrap() {
+        Value::String(x) => assert_eq!(&*x, "done"),
+        x => panic!("{:?}", x),
+    });
+
+    let log = system.call_log();
+    assert_eq!(log.len(), 2);
+    assert_eq!(log[0].args, [("latitude".to_owned(), json!("36.1627")), ("longitude".to_owned(), json!("-86.7816"))]);
+    assert_eq!(log[1].args, [("latitude".to_owned(), json!("40.8136"))]);
+    assert_eq!(log.iter().map(|x| x.time).collect::<Vec<_>>(), [0, 10]);
+}
+
+#[test]
+fn test_proc_system_config_errors() {
+    let config = StdSystemConfigBuilder::default().headers(vec![("bad header".to_owned(), "x".to_owned())]).offline(true).build().unwrap();
+    assert!(matches!(StdSystem::new(config), Err(StdSystemError::Setup { .. })));
+
+    let config = StdSystemConfigBuilder::default().base_url("http://127.0.0.1:1".to_owned()).timeout(Some(std::time::Duration::from_secs(5))).build().unwrap();
+    match StdSystem::new(config) {
+        Err(StdSystemError::Request { url, .. }) => assert_eq!(url, "http://127.0.0.1:1/api/newProject"),
+        x => panic!("{:?}", x.err()),
+    }
+
+    let config = StdSystemConfigBuilder::default().base_url("http://127.0.0.1:1".to_owned()).existing_ids(Some(("proj".to_owned(), "role".to_owned()))).build().unwrap();
+    assert!(StdSystem::new(config).is_ok());
 }
 
 #[test]
 fn test_proc_list_index_blocks() {
-    let system = StdSystem::new("https://editor.netsblox.org".to_owned(), None);
+    let system = MockSystem::new();
     let mut env = get_running_proc(&format!(include_str!("templates/generic-static.xml"),
         globals = "",
         fields = "",
@@ -538,7 +1790,7 @@ fn test_proc_list_index_blocks() {
 
 #[test]
 fn test_proc_literal_types() {
-    let system = StdSystem::new("https://editor.netsblox.org".to_owned(), None);
+    let system = MockSystem::new();
     let mut env = get_running_proc(&format!(include_str!("templates/generic-static.xml"),
         globals = "",
         fields = "",
@@ -554,7 +1806,7 @@ fn test_proc_literal_types() {
 
 #[test]
 fn test_proc_say() {
-    let system = StdSystem::new("https://editor.netsblox.org".to_owned(), None);
+    let system = MockSystem::new();
     let output = Rc::new(RefCell::new(String::new()));
     let output_cpy = output.clone();
     let mut env = get_running_proc(&format!(include_str!("templates/generic-static.xml"),
@@ -567,3 +1819,76 @@ fn test_proc_say() {
     run_till_term(&mut env, &system, |_, _, _| ());
     assert_eq!(output.borrow().as_str(), "\"Greetings, human.\"\n\"I will destroy him.\"\n");
 }
+
+#[test]
+fn test_proc_size_limits() {
+    let system = MockSystem::new();
+    let mut env = get_running_proc(&format!(include_str!("templates/generic-static.xml"),
+        globals = "",
+        fields = "",
+        funcs = include_str!("blocks/size-limits.xml"),
+        methods = "",
+    ), SettingsBuilder::default().max_list_size(Some(100)).max_string_size(Some(1000)).max_heap_size(Some(4096)).build().unwrap());
+
+    for mode in [0, 1, 3, 4, 2] {
+        env.mutate(|mc, env| {
+            let mut locals = SymbolTable::default();
+            locals.redefine_or_define("mode", Shared::Unique((mode as f64).into()));
+            env.proc.write(mc).initialize(locals, None);
+        });
+        run_till_term(&mut env, &system, |mc, _, res| match (mode, res) {
+            (0, Err(ExecError { cause: ErrorCause::ListSizeLimit { size: 1000000000, limit: 100 }, .. })) => (),
+            (1, Err(ExecError { cause: ErrorCause::StringSizeLimit { size: 1024, limit: 1000 }, .. })) => (),
+            (2, Err(ExecError { cause: ErrorCause::HeapSizeLimit { limit: 4096, .. }, .. })) => (),
+            (3, Ok((Some(x), _))) => assert_eq!(x.as_list().unwrap().read().len(), 16),
+            (4, Ok((Some(x), _))) => assert_values_eq(&x, &Value::from_simple(mc, simple_value!([1, 2, 3, 4, 5, 6, 7, 8, 9, 10])), 1e-20, "size limits"),
+            (_, x) => panic!("mode {} - {:?}", mode, x.map(|x| x.0)),
+        });
+    }
+}
+
+#[test]
+fn test_proc_shallow_copy_heap() {
+    let system = MockSystem::new();
+    let mut ins = vec![Instruction::PushNumber { value: 1.0 }, Instruction::MakeList { len: 1 }, Instruction::PushNumber { value: 64.0 }, Instruction::ListReshape];
+    ins.extend((0..8).map(|_| Instruction::ShallowCopy));
+    ins.push(Instruction::Return);
+
+    let mut env = get_running_ins(ins, SettingsBuilder::default().max_heap_size(Some(64 * 8 * core::mem::size_of::<Value>())).build().unwrap());
+    run_till_term(&mut env, &system, |_, _, res| match res {
+        Err(ExecError { cause: ErrorCause::HeapSizeLimit { .. }, .. }) => (),
+        x => panic!("{:?}", x.map(|x| x.0)),
+    });
+}
+
+#[test]
+fn test_proc_sleep() {
+    let system = MockSystem::new();
+    let mut env = get_running_proc(&format!(include_str!("templates/generic-static.xml"),
+        globals = "",
+        fields = "",
+        funcs = include_str!("blocks/sleep.xml"),
+        methods = "",
+    ), SettingsBuilder::default().build().unwrap());
+
+    let start = system.time_ms().unwrap();
+    env.mutate(|mc, env| {
+        let mut proc = env.proc.write(mc);
+        let mut wake_times = BTreeSet::new();
+        let ret = loop {
+            match proc.step(mc, &system).unwrap() {
+                ProcessStep::Normal | ProcessStep::Yield => (),
+                ProcessStep::Waiting { reason: WaitReason::Timer { wake_time } } => {
+                    wake_times.insert(wake_time);
+                    system.advance_by(10);
+                }
+                ProcessStep::Terminate { result } => break result.unwrap(),
+                _ => panic!(),
+            }
+        };
+        assert_values_eq(&ret, &Value::from_simple(mc, simple_value!("5")), 1e-20, "sleep result");
+        assert_eq!(wake_times.len(), 1);
+        assert_eq!(wake_times.into_iter().collect::<Vec<_>>(), [start + 100]);
+    });
+    assert_eq!(system.time_ms().unwrap(), start + 100);
+}
@@ -2,16 +2,18 @@ use std::prelude::v1::*;
 
 use crate::*;
 use crate::gc::*;
+use crate::json::*;
 use crate::runtime::*;
 use crate::process::*;
 use crate::project::*;
+use crate::executor::*;
 
 use super::assert_values_eq;
 
 #[derive(Collect)]
 #[collect(no_drop)]
 struct Env<'gc> {
-    proj: GcCell<'gc, Project<'gc, StdSystem>>,
+    proj: GcCell<'gc, Project<'gc, MockSystem>>,
 }
 make_arena!(EnvArena, Env);
 
@@ -28,18 +30,20 @@ fn get_running_project(xml: &str) -> EnvArena {
     })
 }
 
-fn run_till_term<'gc>(mc: MutationContext<'gc, '_>, proj: &mut Project<'gc, StdSystem>, system: &StdSystem) {
+fn run_till_term<'gc>(mc: MutationContext<'gc, '_>, proj: &mut Project<'gc, MockSystem>, system: &MockSystem) {
     loop {
         match proj.step(mc, &system) {
             ProjectStep::Idle => return,
             ProjectStep::Normal => (),
+            ProjectStep::Waiting { info } => system.advance_to(info.wake_time.into_iter().chain(system.next_event_time()).min().unwrap()),
+            ProjectStep::Error { error } => panic!("{error:?}"),
         }
     }
 }
 
 #[test]
 fn test_proj_counting() {
-    let system = StdSystem::new("https://editor.netsblox.org".to_owned(), None);
+    let system = MockSystem::new();
     let mut proj = get_running_project(include_str!("projects/counting.xml"));
     proj.mutate(|mc, proj| {
         run_till_term(mc, &mut *proj.proj.write(mc), &system);
@@ -56,7 +60,7 @@ fn test_proj_counting() {
 
 #[test]
 fn test_proj_broadcast() {
-    let system = StdSystem::new("https://editor.netsblox.org".to_owned(), None);
+    let system = MockSystem::new();
     let mut proj = get_running_project(include_str!("projects/broadcast.xml"));
     proj.mutate(|mc, proj| {
         run_till_term(mc, &mut *proj.proj.write(mc), &system);
@@ -79,7 +83,15 @@ fn test_proj_broadcast() {
 
 #[test]
 fn test_proj_parallel_rpcs() {
-    let system = StdSystem::new("https://editor.netsblox.org".to_owned(), None);
+    let system = MockSystem::new();
+    for r in 1..=6u32 {
+        for g in 1..=6u32 {
+            for b in 1..=6u32 {
+                let args = [("red", json!(r)), ("green", json!(g)), ("blue", json!(b)), ("alpha", json!(""))];
+                system.add_response("PhoneIoT", "getColor", Some(&args), 5, Ok(json!(((0xff << 24) | (r << 16) | (g << 8) | b) as i32)));
+            }
+        }
+    }
     let mut proj = get_running_project(include_str!("projects/parallel-rpcs.xml"));
     proj.mutate(|mc, proj| {
         run_till_term(mc, &mut *proj.proj.write(mc), &system);
@@ -118,3 +130,589 @@ fn test_proj_parallel_rpcs() {
         }
     });
 }
+
+#[test]
+fn test_proj_executor_counting() {
+    let parser = ast::ParserBuilder::default().build().unwrap();
+    let ast = parser.parse(include_str!("projects/counting.xml")).unwrap();
+    let system = MockSystem::new();
+    let executor_settings = ExecutorSettingsBuilder::default().batch_size(7).build().unwrap();
+    let mut executor = MockExecutor::new(&ast.roles[0], SettingsBuilder::default().build().unwrap(), executor_settings, system);
+    executor.input(Input::Start);
+    executor.run(|error| panic!("{error:?}"));
+    executor.mutate(|mc, proj| {
+        let global_context = proj.global_context();
+        let global_context = global_context.read();
+
+        let expected = Value::from_simple(mc, simple_value!([
+            1, 3, 6, 7, 9, 12, 13, 15, 18, 19, 21, 24, 25, 27, 30, 31, 33, 36, 37, 39, 42, 43, 45, 48, 49, 51, 54, 55, 57, 60,
+        ]));
+        assert_values_eq(&global_context.globals.lookup("res").unwrap().get(), &expected, 1e-20, "res");
+        assert_values_eq(&global_context.globals.lookup("counter").unwrap().get(), &60.0.into(), 1e-20, "counter");
+    });
+}
+
+#[test]
+fn test_proj_executor_gc() {
+    let parser = ast::ParserBuilder::default().build().unwrap();
+    let ast = parser.parse(include_str!("projects/garbage.xml")).unwrap();
+    let system = MockSystem::new();
+    let executor_settings = ExecutorSettingsBuilder::default().batch_size(64).build().unwrap();
+    let settings = SettingsBuilder::default().max_heap_size(Some(1 << 20)).build().unwrap();
+    let mut executor = MockExecutor::new(&ast.roles[0], settings, executor_settings, system);
+    executor.input(Input::Start);
+
+    let mut max_allocated = 0;
+    loop {
+        match executor.step() {
+            ProjectStep::Normal | ProjectStep::Waiting { .. } => max_allocated = max_allocated.max(executor.total_allocated()),
+            ProjectStep::Idle => break,
+            ProjectStep::Error { error } => panic!("{error:?}"),
+        }
+    }
+    assert!(max_allocated < 1 << 20, "{max_allocated}");
+
+    executor.mutate(|_, proj| {
+        let global_context = proj.global_context();
+        let global_context = global_context.read();
+        assert_values_eq(&global_context.globals.lookup("counter").unwrap().get(), &2000.0.into(), 1e-20, "counter");
+        assert!(global_context.heap_usage < 1 << 20, "{}", global_context.heap_usage);
+    });
+}
+
+#[test]
+fn test_proj_executor_heap_limit_live() {
+    let parser = ast::ParserBuilder::default().build().unwrap();
+    let ast = parser.parse(include_str!("projects/live-data.xml")).unwrap();
+    let system = MockSystem::new();
+    let executor_settings = ExecutorSettingsBuilder::default().batch_size(64).build().unwrap();
+    let settings = SettingsBuilder::default().max_heap_size(Some(1 << 20)).build().unwrap();
+    let mut executor = MockExecutor::new(&ast.roles[0], settings, executor_settings, system);
+    executor.input(Input::Start);
+
+    loop {
+        match executor.step() {
+            ProjectStep::Normal | ProjectStep::Waiting { .. } => (),
+            ProjectStep::Idle => panic!("heap size limit was not enforced"),
+            ProjectStep::Error { error } => match error.cause {
+                ErrorCause::HeapSizeLimit { limit, .. } => {
+                    assert_eq!(limit, 1 << 20);
+                    break;
+                }
+                x => panic!("{x:?}"),
+            }
+        }
+    }
+
+    executor.mutate(|_, proj| {
+        let global_context = proj.global_context();
+        let global_context = global_context.read();
+        let counter = global_context.globals.lookup("counter").unwrap().get().to_number().unwrap();
+        assert!(counter > 100.0 && counter < 2000.0, "{counter}");
+        let res = global_context.globals.lookup("res").unwrap().get();
+        assert_eq!(res.as_list().unwrap().read().len(), counter as usize);
+    });
+}
+
+struct MinimalSystem;
+impl System for MinimalSystem {
+    type RpcKey = ();
+
+    fn time_ms(&self) -> Result<u64, SystemError> {
+        Ok(0)
+    }
+    fn call_rpc(&self, _: String, _: String, _: Vec<(String, Json)>) -> Result<Self::RpcKey, SystemError> {
+        Err(SystemError::NotSupported { feature: SystemFeature::Rpc })
+    }
+    fn poll_rpc(&self, _: &Self::RpcKey) -> Result<AsyncPoll<Result<RpcResponse, String>>, SystemError> {
+        unreachable!()
+    }
+}
+make_executor_arena!(MinimalArena, MinimalEnv, MinimalSystem);
+
+#[test]
+fn test_proj_minimal_system() {
+    let parser = ast::ParserBuilder::default().build().unwrap();
+    let ast = parser.parse(include_str!("projects/garbage.xml")).unwrap();
+    let settings = SettingsBuilder::default().build().unwrap();
+    let mut executor = Executor::<MinimalArena>::new(&ast.roles[0], settings, ExecutorSettingsBuilder::default().build().unwrap(), MinimalSystem);
+    executor.input(Input::Start);
+    executor.run(|error| panic!("{error:?}"));
+
+    let system = executor.system();
+    assert!(matches!(system.random(), Err(SystemError::NotSupported { feature: SystemFeature::Random })));
+    assert!(matches!(system.storage(), Err(SystemError::NotSupported { feature: SystemFeature::Storage })));
+    assert!(matches!(system.read_file("a.txt"), Err(SystemError::NotSupported { feature: SystemFeature::Files })));
+    assert!(matches!(system.write_file("a.txt", "hello"), Err(SystemError::NotSupported { feature: SystemFeature::Files })));
+    assert!(matches!(system.list_dir(""), Err(SystemError::NotSupported { feature: SystemFeature::Files })));
+    assert!(system.service_metadata("Chart").is_none());
+    system.cancel_rpc(&());
+}
+
+#[test]
+fn test_proj_waiting() {
+    let parser = ast::ParserBuilder::default().build().unwrap();
+    let ast = parser.parse(include_str!("projects/sleeping.xml")).unwrap();
+    let mut executor = MockExecutor::new(&ast.roles[0], SettingsBuilder::default().build().unwrap(), ExecutorSettingsBuilder::default().build().unwrap(), MockSystem::new());
+    let start = executor.system().time_ms().unwrap();
+    executor.input(Input::Start);
+
+    let mut waits = vec![];
+    loop {
+        match executor.step() {
+            ProjectStep::Normal => (),
+            ProjectStep::Waiting { info } => {
+                let wake_time = info.wake_time.unwrap();
+                waits.push((info.rpcs, info.timers, info.barriers, wake_time - start));
+                executor.system().advance_to(wake_time);
+            }
+            ProjectStep::Idle => break,
+            ProjectStep::Error { error } => panic!("{error:?}"),
+        }
+    }
+    assert_eq!(waits, [(0, 2, 0, 100), (0, 1, 0, 200)]);
+    assert_eq!(executor.system().time_ms().unwrap(), start + 200);
+
+    executor.mutate(|mc, proj| {
+        let global_context = proj.global_context();
+        let global_context = global_context.read();
+        assert_values_eq(&global_context.globals.lookup("counter").unwrap().get(), &Value::from_simple(mc, simple_value!("1")), 1e-20, "counter");
+        assert_values_eq(&global_context.globals.lookup("res").unwrap().get(), &Value::from_simple(mc, simple_value!("2")), 1e-20, "res");
+    });
+}
+
+#[test]
+fn test_proj_barrier_waker() {
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::task::Wake;
+
+    struct CountingWaker(AtomicUsize);
+    impl Wake for CountingWaker {
+        fn wake(self: Arc<Self>) { self.0.fetch_add(1, Ordering::SeqCst); }
+    }
+    let counter = Arc::new(CountingWaker(AtomicUsize::new(0)));
+    let waker = counter.clone().into();
+
+    let barrier = Barrier::new();
+    let other = barrier.clone();
+    let condition = barrier.get_condition();
+    condition.register_waker(&waker);
+    drop(barrier);
+    assert_eq!(counter.0.load(Ordering::SeqCst), 0);
+    drop(other);
+    assert_eq!(counter.0.load(Ordering::SeqCst), 1);
+    assert!(condition.is_completed());
+    condition.register_waker(&waker);
+    assert_eq!(counter.0.load(Ordering::SeqCst), 2);
+
+    // a released barrier must not leave the executor parked
+    let parser = ast::ParserBuilder::default().build().unwrap();
+    let ast = parser.parse(include_str!("projects/sleeping.xml")).unwrap();
+    let executor = MockExecutor::new(&ast.roles[0], SettingsBuilder::default().build().unwrap(), ExecutorSettingsBuilder::default().build().unwrap(), MockSystem::new());
+    executor.wait(&WaitInfo { barriers: 1, conditions: vec![condition], ..Default::default() });
+}
+
+#[tokio::test]
+async fn test_proj_run_async() {
+    let parser = ast::ParserBuilder::default().build().unwrap();
+    let ast = parser.parse(include_str!("projects/sleeping.xml")).unwrap();
+
+    let mut executors = vec![];
+    for _ in 0..4 {
+        let system = StdSystem::new_async(StdSystemConfigBuilder::default().offline(true).build().unwrap()).await.unwrap();
+        let mut executor = StdExecutor::new(&ast.roles[0], SettingsBuilder::default().build().unwrap(), ExecutorSettingsBuilder::default().build().unwrap(), system);
+        executor.input(Input::Start);
+        executors.push(executor);
+    }
+
+    // the executors run concurrently iff they all start before any of them finishes
+    let log = std::cell::RefCell::new(vec![]);
+    async fn run(i: usize, executor: &mut StdExecutor, log: &std::cell::RefCell<Vec<(&str, usize)>>) {
+        log.borrow_mut().push(("start", i));
+        executor.run_async(|error| panic!("{error:?}")).await;
+        log.borrow_mut().push(("done", i));
+    }
+    let start = std::time::Instant::now();
+    match executors.as_mut_slice() {
+        [a, b, c, d] => tokio::join!(run(0, a, &log), run(1, b, &log), run(2, c, &log), run(3, d, &log)),
+        _ => unreachable!(),
+    };
+    assert!(start.elapsed().as_millis() >= 200);
+    let log = log.into_inner();
+    assert_eq!(log[..4], [("start", 0), ("start", 1), ("start", 2), ("start", 3)]);
+    assert!(log[4..].iter().all(|x| x.0 == "done"), "{log:?}");
+
+    for executor in executors.iter_mut() {
+        executor.mutate(|mc, proj| {
+            let global_context = proj.global_context();
+            let global_context = global_context.read();
+            assert_values_eq(&global_context.globals.lookup("counter").unwrap().get(), &Value::from_simple(mc, simple_value!("1")), 1e-20, "counter");
+            assert_values_eq(&global_context.globals.lookup("res").unwrap().get(), &Value::from_simple(mc, simple_value!("2")), 1e-20, "res");
+        });
+    }
+}
+
+#[test]
+fn test_proj_run_local_set() {
+    let parser = ast::ParserBuilder::default().build().unwrap();
+    let ast = parser.parse(include_str!("projects/sleeping.xml")).unwrap();
+    let runtime = tokio::runtime::Builder::new_current_thread().enable_all().build().unwrap();
+    let local = tokio::task::LocalSet::new();
+
+    let log = std::rc::Rc::new(std::cell::RefCell::new(vec![]));
+    local.block_on(&runtime, async {
+        // systems are Send, so they can be created by ordinary tasks and handed to the (!Send) executors
+        let systems = tokio::spawn(async {
+            let mut systems = vec![];
+            for _ in 0..4 {
+                systems.push(StdSystem::new_async(StdSystemConfigBuilder::default().offline(true).build().unwrap()).await.unwrap());
+            }
+            systems
+        }).await.unwrap();
+
+        let tasks: Vec<_> = systems.into_iter().enumerate().map(|(i, system)| {
+            let (role, log) = (ast.roles[0].clone(), log.clone());
+            tokio::task::spawn_local(async move {
+                let mut executor = StdExecutor::new(&role, SettingsBuilder::default().build().unwrap(), ExecutorSettingsBuilder::default().build().unwrap(), system);
+                executor.input(Input::Start);
+                log.borrow_mut().push(("start", i));
+                executor.run_async(|error| panic!("{error:?}")).await;
+                log.borrow_mut().push(("done", i));
+                executor.mutate(|mc, proj| {
+                    let global_context = proj.global_context();
+                    let global_context = global_context.read();
+                    assert_values_eq(&global_context.globals.lookup("res").unwrap().get(), &Value::from_simple(mc, simple_value!("2")), 1e-20, "res");
+                });
+            })
+        }).collect();
+        for task in tasks {
+            task.await.unwrap();
+        }
+    });
+
+    let log = log.borrow();
+    assert_eq!(log.len(), 8);
+    assert!(log[..4].iter().all(|x| x.0 == "start") && log[4..].iter().all(|x| x.0 == "done"), "{log:?}");
+}
+
+make_executor_arena!(RecordArena, RecordEnv, RecordSystem<MockSystem>);
+make_executor_arena!(ReplayArena, ReplayEnv, ReplaySystem<MockSystem>);
+
+fn run_with_mock<A: ExecutorArena>(executor: &mut Executor<A>, get_mock: fn(&A::System) -> &MockSystem) -> Vec<ExecError> {
+    let mut errors = vec![];
+    loop {
+        match executor.step() {
+            ProjectStep::Idle => return errors,
+            ProjectStep::Normal => (),
+            ProjectStep::Waiting { info } => {
+                let mock = get_mock(executor.system());
+                if let Some(time) = info.wake_time.into_iter().chain(mock.next_event_time()).min() { mock.advance_to(time) }
+            }
+            ProjectStep::Error { error } => errors.push(error),
+        }
+    }
+}
+
+#[test]
+fn test_proj_record_replay() {
+    let parser = ast::ParserBuilder::default().build().unwrap();
+    let ast = parser.parse(include_str!("projects/parallel-rpcs.xml")).unwrap();
+    let get_output = |global_context: &GlobalContext| {
+        // results arrive in a different order during replay due to latency, so compare them as sets
+        let mut rows: Vec<_> = global_context.globals.lookup("output").unwrap().get().to_simple().unwrap().into_list().unwrap().into_iter().map(|x| format!("{x:?}")).collect();
+        rows.sort();
+        rows
+    };
+
+    let mock = MockSystem::new();
+    for r in 1..=6u32 {
+        for g in 1..=6u32 {
+            for b in 1..=6u32 {
+                let args = [("red", json!(r)), ("green", json!(g)), ("blue", json!(b)), ("alpha", json!(""))];
+                let result = if (r + g + b) % 5 == 0 { Err(format!("error {r} {g} {b}")) } else { Ok(json!([r, g, b])) };
+                mock.add_response("PhoneIoT", "getColor", Some(&args), (r + g + b) as u64, result);
+            }
+        }
+    }
+    let mut executor = Executor::<RecordArena>::new(&ast.roles[0], SettingsBuilder::default().build().unwrap(), ExecutorSettingsBuilder::default().build().unwrap(), RecordSystem::new(mock));
+    executor.input(Input::Start);
+    assert!(run_with_mock(&mut executor, RecordSystem::inner).is_empty());
+    let expected = executor.mutate(|_, proj| get_output(&proj.global_context().read()));
+
+    let path = std::env::temp_dir().join(format!("netsblox-vm-record-{}.json", std::process::id()));
+    let path = path.to_str().unwrap();
+    executor.system().save(path).unwrap();
+    let recording = executor.system().to_json();
+    assert_eq!(recording["rpcs"].as_array().unwrap().len(), 216);
+
+    let mut executor = Executor::<ReplayArena>::new(&ast.roles[0], SettingsBuilder::default().build().unwrap(), ExecutorSettingsBuilder::default().build().unwrap(), ReplaySystem::load(path, MockSystem::new()).unwrap());
+    std::fs::remove_file(path).unwrap();
+    executor.input(Input::Start);
+    assert!(run_with_mock(&mut executor, ReplaySystem::inner).is_empty());
+    assert_eq!(executor.system().remaining(), 0);
+    assert_eq!(executor.system().inner().call_log().len(), 0);
+    let got = executor.mutate(|_, proj| get_output(&proj.global_context().read()));
+    assert_eq!(got, expected);
+
+    let mut recording = recording;
+    recording["rpcs"][0]["rpc"] = json!("getColour");
+    let mut executor = Executor::<ReplayArena>::new(&ast.roles[0], SettingsBuilder::default().build().unwrap(), ExecutorSettingsBuilder::default().build().unwrap(), ReplaySystem::from_json(&recording, MockSystem::new()).unwrap());
+    executor.input(Input::Start);
+    let errors = run_with_mock(&mut executor, ReplaySystem::inner);
+    assert_eq!(errors.len(), 4); // one per thread, since the mismatched request is never consumed
+    for error in errors {
+        assert!(matches!(error.cause, ErrorCause::SystemError { error: SystemError::Other { .. } }), "{error:?}");
+    }
+    assert_eq!(executor.system().remaining(), 216);
+
+    assert!(matches!(ReplaySystem::from_json(&json!({ "rpcs": [{ "service": "a" }] }), MockSystem::new()), Err(RecordingError::BadFormat)));
+    assert!(matches!(ReplaySystem::from_json(&json!({ "rpcs": [], "random": ["a"] }), MockSystem::new()), Err(RecordingError::BadFormat)));
+}
+
+#[test]
+fn test_proj_record_replay_random() {
+    let system = RecordSystem::new(MockSystem::new());
+    let expected = (0..5).map(|_| system.random().unwrap()).collect::<Vec<_>>();
+    let recording = system.to_json();
+    assert_eq!(recording["random"].as_array().unwrap().len(), 5);
+
+    let system = ReplaySystem::from_json(&recording, MockSystem::new()).unwrap();
+    system.inner().random().unwrap(); // the wrapped system is never consulted
+    let got = (0..5).map(|_| system.random().unwrap()).collect::<Vec<_>>();
+    assert_eq!(got, expected);
+    assert!(matches!(system.random(), Err(SystemError::Other { .. })));
+}
+
+make_executor_arena!(RoutingArena, RoutingEnv, RoutingSystem<MockSystem>);
+
+#[test]
+fn test_proj_routing_system() {
+    let parser = ast::ParserBuilder::default().build().unwrap();
+    let ast = parser.parse(include_str!("projects/parallel-rpcs.xml")).unwrap();
+
+    let encode = |r: u32, g: u32, b: u32| ((0xff << 24) | (r << 16) | (g << 8) | b) as i32;
+    let mock = MockSystem::new();
+    for r in (2..=6u32).step_by(2) {
+        for g in 1..=6u32 {
+            for b in 1..=6u32 {
+                let args = [("red", json!(r)), ("green", json!(g)), ("blue", json!(b)), ("alpha", json!(""))];
+                mock.add_response("PhoneIoT", "getColor", Some(&args), 3, Ok(json!(encode(r, g, b))));
+            }
+        }
+    }
+    let system = RoutingSystem::new(mock)
+        .with_provider(|service: &str, _: &str, _: &[(String, Json)]| match service {
+            "Geolocation" => Some(Err("not available".to_owned())),
+            _ => None,
+        })
+        .with_provider(move |service: &str, rpc: &str, args: &[(String, Json)]| {
+            if (service, rpc) != ("PhoneIoT", "getColor") { return None }
+            let arg = |name: &str| args.iter().find(|x| x.0 == name).and_then(|x| x.1.as_f64()).map(|x| x as u32);
+            let (r, g, b) = (arg("red")?, arg("green")?, arg("blue")?);
+            match r % 2 {
+                0 => None,
+                _ => Some(Ok(json!(encode(r, g, b)))),
+            }
+        });
+    assert!(matches!(system.call_rpc("Geolocation".to_owned(), "city".to_owned(), vec![]).map(|x| system.poll_rpc(&x)), Ok(Ok(AsyncPoll::Completed(Err(_))))));
+
+    let mut executor = Executor::<RoutingArena>::new(&ast.roles[0], SettingsBuilder::default().build().unwrap(), ExecutorSettingsBuilder::default().build().unwrap(), system);
+    executor.input(Input::Start);
+    assert!(run_with_mock(&mut executor, RoutingSystem::inner).is_empty());
+
+    let log = executor.system().inner().call_log();
+    assert_eq!(log.len(), 108);
+    assert!(log.iter().all(|x| (x.args[0].1.as_f64().unwrap() as u32).is_multiple_of(2)));
+
+    executor.mutate(|_, proj| {
+        let global_context = proj.global_context();
+        let global_context = global_context.read();
+        let output = global_context.globals.lookup("output").unwrap().get().to_simple().unwrap().into_list().unwrap();
+        assert_eq!(output.len(), 216);
+        for row in output {
+            let vals: Vec<_> = row.into_list().unwrap().into_iter().map(|x| x.as_number().unwrap() as i64).collect();
+            assert_eq!(vals[3], encode(vals[0] as u32, vals[1] as u32, vals[2] as u32) as i64);
+        }
+    });
+}
+
+#[test]
+fn test_proj_cloud_variables() {
+    let system = RoutingSystem::new(MockSystem::new()).with_cloud_variables();
+    let call = |rpc: &str, args: &[(&str, Json)]| {
+        let key = system.call_rpc("CloudVariables".to_owned(), rpc.to_owned(), args.iter().map(|x| (x.0.to_owned(), x.1.clone())).collect()).unwrap();
+        match system.poll_rpc(&key).unwrap() {
+            AsyncPoll::Completed(Ok(RpcResponse::Json(x))) => Ok(x),
+            AsyncPoll::Completed(Ok(x)) => panic!("{x:?}"),
+            AsyncPoll::Completed(Err(x)) => Err(x),
+            AsyncPoll::Pending => panic!(),
+        }
+    };
+
+    assert_eq!(call("getVariable", &[("name", json!("score")), ("password", json!(""))]), Err("Variable not found".to_owned()));
+    assert_eq!(call("setVariable", &[("name", json!("score")), ("value", json!(12)), ("password", json!("secret"))]), Ok(json!("")));
+    assert_eq!(call("getVariable", &[("name", json!("score")), ("password", json!("secret"))]), Ok(json!(12)));
+    assert_eq!(call("getVariable", &[("name", json!("score")), ("password", json!("wrong"))]), Err("Incorrect password".to_owned()));
+    assert_eq!(call("setVariable", &[("name", json!("score")), ("value", json!(13)), ("password", json!("wrong"))]), Err("Incorrect password".to_owned()));
+    assert_eq!(call("setVariable", &[("name", json!("score")), ("value", json!([1, "two"])), ("password", json!("secret"))]), Ok(json!("")));
+    assert_eq!(call("getVariable", &[("name", json!("score")), ("password", json!("secret"))]), Ok(json!([1, "two"])));
+    assert_eq!(call("deleteVariable", &[("name", json!("score")), ("password", json!("secret"))]), Ok(json!("")));
+    assert_eq!(call("getVariable", &[("name", json!("score")), ("password", json!("secret"))]), Err("Variable not found".to_owned()));
+
+    assert_eq!(call("setUserVariable", &[("name", json!("color")), ("value", json!("red"))]), Ok(json!("")));
+    assert_eq!(call("getUserVariable", &[("name", json!("color"))]), Ok(json!("red")));
+    assert_eq!(call("getVariable", &[("name", json!("color")), ("password", json!(""))]), Err("Variable not found".to_owned()));
+    assert_eq!(call("deleteUserVariable", &[("name", json!("color"))]), Ok(json!("")));
+    assert_eq!(call("deleteUserVariable", &[("name", json!("color"))]), Err("Variable not found".to_owned()));
+
+    assert!(call("lockVariable", &[("name", json!("color")), ("password", json!(""))]).is_err());
+    assert_eq!(system.inner().storage().unwrap().list("CloudVariables").unwrap(), Vec::<String>::new());
+    assert!(system.inner().call_log().is_empty());
+
+    let system = RoutingSystem::new(MockSystem::new());
+    system.inner().add_response("CloudVariables", "getVariable", None, 0, Ok(json!("remote")));
+    assert!(system.call_rpc("CloudVariables".to_owned(), "getVariable".to_owned(), vec![]).is_ok());
+    assert_eq!(system.inner().call_log().len(), 1);
+}
+
+#[test]
+fn test_proj_file_storage() {
+    extern crate std as real_std;
+    let root = real_std::env::temp_dir().join(format!("netsblox-vm-storage-{}", real_std::process::id()));
+    let root = root.to_str().unwrap();
+    let _ = real_std::fs::remove_dir_all(root);
+
+    let storage = FileStorage::new(root);
+    assert_eq!(storage.get("vars", "x").unwrap(), None);
+    assert_eq!(storage.list("vars").unwrap(), Vec::<String>::new());
+    storage.set("vars", "x", json!(5)).unwrap();
+    storage.set("vars", "y", json!({ "a": [1, 2] })).unwrap();
+    storage.set("../other/ns", "x", json!("hello")).unwrap();
+    assert!(!storage.delete("vars", "z").unwrap());
+
+    let storage = FileStorage::new(root);
+    assert_eq!(storage.get("vars", "x").unwrap(), Some(json!(5)));
+    assert_eq!(storage.get("vars", "y").unwrap(), Some(json!({ "a": [1, 2] })));
+    assert_eq!(storage.get("../other/ns", "x").unwrap(), Some(json!("hello")));
+    assert_eq!(storage.list("vars").unwrap(), ["x", "y"]);
+    assert!(storage.delete("vars", "x").unwrap());
+    assert_eq!(storage.list("vars").unwrap(), ["y"]);
+    assert_eq!(real_std::fs::read_dir(root).unwrap().count(), 2);
+
+    real_std::fs::remove_dir_all(root).unwrap();
+}
+
+#[test]
+fn test_proj_file_sandbox() {
+    extern crate std as real_std;
+    let dir = real_std::env::temp_dir().join(format!("netsblox-vm-files-{}", real_std::process::id()));
+    let _ = real_std::fs::remove_dir_all(&dir);
+    real_std::fs::create_dir_all(dir.join("root")).unwrap();
+    real_std::fs::write(dir.join("secret.txt"), "secret").unwrap();
+    let root = dir.join("root");
+
+    let files = FileSandbox::new(root.to_str().unwrap());
+    files.write_file("data.csv", "a,b\n1,2\n").unwrap();
+    files.write_file("/out/nested/result.txt", "done").unwrap();
+    assert_eq!(files.read_file("data.csv").unwrap(), "a,b\n1,2\n");
+    assert_eq!(files.read_file("out/./nested/../nested/result.txt").unwrap(), "done");
+    assert_eq!(files.list_dir("").unwrap(), ["data.csv", "out/"]);
+    assert_eq!(files.list_dir("/out").unwrap(), ["nested/"]);
+    assert!(files.read_file("missing.txt").is_err());
+
+    assert!(files.read_file("../secret.txt").is_err());
+    assert!(files.read_file("out/../../secret.txt").is_err());
+    assert!(files.write_file("../escaped.txt", "oops").is_err());
+    assert!(files.list_dir("..").is_err());
+    assert!(!dir.join("escaped.txt").exists());
+
+    #[cfg(unix)]
+    {
+        real_std::os::unix::fs::symlink(&dir, root.join("link")).unwrap();
+        assert!(files.read_file("link/secret.txt").is_err());
+        assert!(files.write_file("link/escaped.txt", "oops").is_err());
+        assert!(!dir.join("escaped.txt").exists());
+
+        real_std::os::unix::fs::symlink(dir.join("dangling.txt"), root.join("dangling.txt")).unwrap();
+        assert!(files.write_file("dangling.txt", "oops").is_err());
+        assert!(files.read_file("dangling.txt").is_err());
+        assert!(!dir.join("dangling.txt").exists());
+
+        real_std::os::unix::fs::symlink(root.join("out"), root.join("inner")).unwrap();
+        assert!(files.read_file("inner/nested/result.txt").is_err());
+        assert!(files.write_file("inner/new.txt", "oops").is_err());
+        assert!(files.list_dir("inner").is_err());
+        assert!(!root.join("out/new.txt").exists());
+    }
+
+    let system = MockSystem::new();
+    assert!(matches!(system.read_file("data.csv"), Err(SystemError::NotSupported { feature: SystemFeature::Files })));
+    assert!(matches!(RoutingSystem::new(system).list_dir(""), Err(SystemError::NotSupported { feature: SystemFeature::Files })));
+
+    real_std::fs::remove_dir_all(&dir).unwrap();
+}
+
+make_executor_arena!(FilesArena, FilesEnv, RoutingSystem<StdSystem>);
+
+#[test]
+fn test_proj_files_service() {
+    extern crate std as real_std;
+    let dir = real_std::env::temp_dir().join(format!("netsblox-vm-files-service-{}", real_std::process::id()));
+    let _ = real_std::fs::remove_dir_all(&dir);
+    real_std::fs::create_dir_all(dir.join("root")).unwrap();
+    real_std::fs::write(dir.join("secret.txt"), "secret").unwrap();
+    real_std::fs::write(dir.join("root/data.txt"), "some data").unwrap();
+    let root = dir.join("root");
+
+    let config = StdSystemConfigBuilder::default().offline(true).file_root(Some(root.to_str().unwrap().to_owned())).build().unwrap();
+    let system = RoutingSystem::new(StdSystem::new(config).unwrap()).with_files();
+    let parser = ast::ParserBuilder::default().build().unwrap();
+    let ast = parser.parse(include_str!("projects/files.xml")).unwrap();
+    let mut executor = Executor::<FilesArena>::new(&ast.roles[0], SettingsBuilder::default().build().unwrap(), ExecutorSettingsBuilder::default().build().unwrap(), system);
+    executor.input(Input::Start);
+    executor.run(|error| panic!("{error:?}"));
+
+    executor.mutate(|_, proj| {
+        let global_context = proj.global_context();
+        let global_context = global_context.read();
+        let get = |name: &str| global_context.globals.lookup(name).unwrap().get().to_simple().unwrap();
+        assert_eq!(get("content").as_str(), Some("hello world"));
+        assert_eq!(get("data").as_str(), Some("some data"));
+        assert_eq!(get("listing").into_list().unwrap().into_iter().map(|x| x.as_str().unwrap().to_owned()).collect::<Vec<_>>(), ["data.txt", "notes/"]);
+        assert_ne!(get("escaped").as_str(), Some("secret"));
+    });
+    assert_eq!(real_std::fs::read_to_string(root.join("notes/hello.txt")).unwrap(), "hello world");
+
+    let system = RoutingSystem::new(MockSystem::new()).with_files();
+    assert!(matches!(system.call_rpc("Files".to_owned(), "readFile".to_owned(), vec![]), Err(SystemError::NotSupported { feature: SystemFeature::Files })));
+
+    real_std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_proj_stop_cancels_rpcs() {
+    let system = MockSystem::new();
+    system.add_response("PhoneIoT", "getColor", None, 1000, Ok(json!(0)));
+    let mut proj = get_running_project(include_str!("projects/parallel-rpcs.xml"));
+    proj.mutate(|mc, proj| {
+        let mut proj = proj.proj.write(mc);
+        while system.pending_rpcs() < 4 {
+            match proj.step(mc, &system) {
+                ProjectStep::Normal | ProjectStep::Waiting { .. } => (),
+                _ => panic!(),
+            }
+        }
+        match proj.step(mc, &system) {
+            ProjectStep::Normal => (),
+            ProjectStep::Waiting { info } => assert_eq!((info.rpcs, info.timers, info.barriers, info.wake_time), (4, 0, 0, None)),
+            _ => panic!(),
+        }
+
+        proj.input(Input::Stop);
+        assert_eq!(system.pending_rpcs(), 4);
+        assert!(matches!(proj.step(mc, &system), ProjectStep::Idle));
+        assert_eq!(system.pending_rpcs(), 0);
+    });
+    assert_eq!(system.call_log().len(), 4);
+}